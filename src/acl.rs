@@ -0,0 +1,86 @@
+//! Access lists, and the interfaces filtering packets through them
+
+use std::fmt::Write;
+
+use ipnet::IpNet;
+
+use crate::{AclProtocol, AclRule, DeviceContext, DirectedLink};
+
+pub(crate) fn write_acls(ctx: &DeviceContext, res: &mut String) {
+    for acl in &ctx.device.acls {
+        let extended = acl.rules.iter().any(AclRule::is_extended);
+        if let Ok(number) = acl.name.parse::<u16>() {
+            let in_range = if extended {
+                (100..=199).contains(&number) || (2000..=2699).contains(&number)
+            } else {
+                (1..=99).contains(&number) || (1300..=1999).contains(&number)
+            };
+            let kind = if extended { "extended" } else { "standard" };
+            assert!(
+                in_range,
+                "{number} is not the number of a {kind} access list"
+            );
+
+            for rule in &acl.rules {
+                writeln!(res, "access-list {number} {}", format_rule(rule, extended)).unwrap();
+            }
+        } else {
+            let kind = if extended { "extended" } else { "standard" };
+            writeln!(res, "ip access-list {kind} {}", acl.name).unwrap();
+            for rule in &acl.rules {
+                writeln!(res, "   {}", format_rule(rule, extended)).unwrap();
+            }
+            res.push_str("exit\n");
+        }
+    }
+    if !ctx.device.acls.is_empty() {
+        res.push('\n');
+    }
+}
+
+pub(crate) fn write_interface(res: &mut String, link: &DirectedLink) {
+    if let Some(acl) = &link.close_options.acl_in {
+        writeln!(res, "   ip access-group {acl} in").unwrap();
+    }
+    if let Some(acl) = &link.close_options.acl_out {
+        writeln!(res, "   ip access-group {acl} out").unwrap();
+    }
+}
+
+fn format_rule(rule: &AclRule, extended: bool) -> String {
+    let action = rule.action.keyword();
+    if !extended {
+        return format!("{action} {}", format_address(rule.src));
+    }
+
+    let protocol = match rule.protocol.unwrap_or(AclProtocol::Ip) {
+        AclProtocol::Ip => "ip",
+        AclProtocol::Tcp => "tcp",
+        AclProtocol::Udp => "udp",
+        AclProtocol::Icmp => "icmp",
+    };
+    if rule.src_port.is_some() || rule.dst_port.is_some() {
+        assert!(
+            matches!(rule.protocol, Some(AclProtocol::Tcp | AclProtocol::Udp)),
+            "Only TCP and UDP rules can match ports"
+        );
+    }
+    let mut res = format!("{action} {protocol} {}", format_address(rule.src));
+    if let Some(port) = rule.src_port {
+        write!(res, " eq {port}").unwrap();
+    }
+    write!(res, " {}", format_address(rule.dst)).unwrap();
+    if let Some(port) = rule.dst_port {
+        write!(res, " eq {port}").unwrap();
+    }
+    res
+}
+
+/// The address of a rule, with its wildcard mask
+fn format_address(address: Option<IpNet>) -> String {
+    match address {
+        None => "any".to_string(),
+        Some(net) if net.prefix_len() == net.max_prefix_len() => format!("host {}", net.addr()),
+        Some(net) => format!("{} {}", net.network(), net.hostmask()),
+    }
+}
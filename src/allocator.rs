@@ -0,0 +1,78 @@
+//! Allocation of the subnets of the links from an address pool
+
+use ipnet::IpNet;
+
+/// A block of addresses, carved into subnets of the same size
+#[derive(Debug)]
+pub(crate) struct AddressPool {
+    pool: IpNet,
+    prefix_len: u8,
+    /// The subnets which can't be allocated, either because they were already
+    /// allocated or because they were assigned by hand
+    used: Vec<IpNet>,
+}
+
+impl AddressPool {
+    pub(crate) fn new(pool: IpNet, prefix_len: u8) -> Self {
+        assert!(
+            prefix_len >= pool.prefix_len() && prefix_len <= pool.max_prefix_len(),
+            "Subnets of /{prefix_len} can't be carved out of {pool}"
+        );
+        AddressPool {
+            pool: pool.trunc(),
+            prefix_len,
+            used: vec![],
+        }
+    }
+
+    /// The block the subnets are carved out of
+    pub(crate) fn block(&self) -> IpNet {
+        self.pool
+    }
+
+    /// Keep the allocated subnets from overlapping with `subnet`
+    pub(crate) fn reserve(&mut self, subnet: IpNet) {
+        self.used.push(subnet.trunc());
+    }
+
+    /// Take the first subnet of the pool which doesn't overlap with the used ones
+    pub(crate) fn allocate(&mut self) -> Option<IpNet> {
+        self.allocate_sized(self.prefix_len)
+    }
+
+    /// Take the first subnet of the pool with the given prefix length
+    /// which doesn't overlap with the used ones
+    fn allocate_sized(&mut self, prefix_len: u8) -> Option<IpNet> {
+        let subnet = self
+            .pool
+            .subnets(prefix_len)
+            .ok()?
+            .find(|subnet| !self.used.iter().any(|used| overlaps(used, subnet)))?;
+        self.used.push(subnet);
+        Some(subnet)
+    }
+}
+
+/// Carve out of `block` the smallest subnet with room for each of the
+/// given numbers of hosts, returning them in the same order
+///
+/// The largest subnets are allocated first, so that the smaller ones
+/// fill the gaps between them rather than fragmenting the block
+pub(crate) fn plan_vlsm(block: IpNet, hosts: &[u32]) -> Option<Vec<IpNet>> {
+    assert!(block.addr().is_ipv4(), "VLSM is only supported for IPv4");
+    let mut pool = AddressPool::new(block, block.prefix_len());
+
+    let mut order: Vec<usize> = (0..hosts.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(hosts[i]));
+    let mut subnets = vec![None; hosts.len()];
+    for i in order {
+        // The network and broadcast addresses can't be given to hosts
+        let host_bits = (0..=32).find(|bits| (1u64 << bits) >= u64::from(hosts[i]) + 2)?;
+        subnets[i] = Some(pool.allocate_sized(32 - host_bits)?);
+    }
+    subnets.into_iter().collect()
+}
+
+fn overlaps(a: &IpNet, b: &IpNet) -> bool {
+    a.contains(&b.network()) || b.contains(&a.network())
+}
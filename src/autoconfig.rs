@@ -0,0 +1,69 @@
+//! IPv6 autoconfiguration of the hosts behind the interfaces of a router,
+//! through router advertisements and DHCPv6
+
+use std::fmt::Write;
+
+use ipnet::IpNet;
+
+use crate::{DeviceContext, DirectedLink, Ipv6AddressingMode};
+
+/// The name of the DHCPv6 pool serving the LAN behind the interface of `link`
+fn pool_name(link: &DirectedLink) -> String {
+    format!("DHCPV6-{}", link.close_iface)
+}
+
+/// The IPv6 address of the close side of the link
+fn ipv6_address(link: &DirectedLink) -> IpNet {
+    [Some(link.close_ip), link.close_ipv6]
+        .into_iter()
+        .flatten()
+        .find(|ip| ip.addr().is_ipv6())
+        .expect("IPv6 autoconfiguration requires an IPv6 subnet")
+}
+
+pub(crate) fn write_pools(ctx: &DeviceContext, res: &mut String) {
+    for link in &ctx.links {
+        let Some(config) = &link.close_options.ipv6_autoconfig else {
+            continue;
+        };
+        if config.mode == Ipv6AddressingMode::Slaac {
+            continue;
+        }
+
+        writeln!(res, "ipv6 dhcp pool {}", pool_name(link)).unwrap();
+        if config.mode == Ipv6AddressingMode::Stateful {
+            writeln!(res, "   address prefix {}", ipv6_address(link).trunc()).unwrap();
+        }
+        if let Some(dns_server) = config.dns_server {
+            writeln!(res, "   dns-server {dns_server}").unwrap();
+        }
+        if let Some(domain_name) = &config.domain_name {
+            writeln!(res, "   domain-name {domain_name}").unwrap();
+        }
+        res.push_str("exit\n\n");
+    }
+}
+
+pub(crate) fn write_interface(res: &mut String, link: &DirectedLink) {
+    let Some(config) = &link.close_options.ipv6_autoconfig else {
+        return;
+    };
+
+    // The flags of the router advertisements tell the hosts what to ask DHCPv6
+    match config.mode {
+        Ipv6AddressingMode::Slaac => {
+            res.push_str("   no ipv6 nd managed-config-flag\n");
+            res.push_str("   no ipv6 nd other-config-flag\n");
+        }
+        Ipv6AddressingMode::Stateless => {
+            res.push_str("   ipv6 nd other-config-flag\n");
+        }
+        Ipv6AddressingMode::Stateful => {
+            res.push_str("   ipv6 nd managed-config-flag\n");
+            res.push_str("   ipv6 nd prefix default no-autoconfig\n");
+        }
+    }
+    if config.mode != Ipv6AddressingMode::Slaac {
+        writeln!(res, "   ipv6 dhcp server {}", pool_name(link)).unwrap();
+    }
+}
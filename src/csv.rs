@@ -0,0 +1,239 @@
+//! Import of topologies from the spreadsheets of their devices and links
+//!
+//! `devices.csv` has the columns `name`, `kind`, `platform`, `role`, `x` and `y`,
+//! and `links.csv` the columns `r1`, `r2`, `ip`, `ospf` and `cost`. The first
+//! line names the columns, which can be in any order: only `name`, `r1`, `r2`
+//! and `ip` are required, the others can be missing or left empty
+
+use std::{collections::HashMap, str::FromStr};
+
+use ipnet::IpNet;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use slotmap::DefaultKey;
+
+use crate::App;
+
+const DEVICE_COLUMNS: &[&str] = &["name", "kind", "platform", "role", "x", "y"];
+const LINK_COLUMNS: &[&str] = &["r1", "r2", "ip", "ospf", "cost"];
+
+/// Build the topology described by the contents of `devices.csv` and `links.csv`,
+/// or report every row which can't be imported
+///
+/// The devices without a position are placed by `App::auto_layout`
+pub fn import(devices: &str, links: &str) -> Result<App, Vec<String>> {
+    let mut app = App::new();
+    let mut errors = vec![];
+
+    let mut keys = HashMap::new();
+    let mut unplaced = vec![];
+    for row in rows("devices.csv", devices, DEVICE_COLUMNS, &mut errors) {
+        match add_device(&mut app, &keys, &row) {
+            Ok((key, placed)) => {
+                keys.insert(app.devices[key].name.clone(), key);
+                if !placed {
+                    unplaced.push(key);
+                }
+            }
+            Err(e) => errors.push(row.error(&e)),
+        }
+    }
+
+    for row in rows("links.csv", links, LINK_COLUMNS, &mut errors) {
+        if let Err(e) = add_link(&mut app, &keys, &row) {
+            errors.push(row.error(&e));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    app.auto_layout(&unplaced);
+    Ok(app)
+}
+
+/// Add the device of a row, returning whether it has a position
+fn add_device(
+    app: &mut App,
+    keys: &HashMap<String, DefaultKey>,
+    row: &Row,
+) -> Result<(DefaultKey, bool), String> {
+    let name = row.required("name")?;
+    if keys.contains_key(name) {
+        return Err(format!("the device `{name}` is declared twice"));
+    }
+    let position = match (row.parse::<f32>("x")?, row.parse::<f32>("y")?) {
+        (Some(x), Some(y)) => Some((x, y)),
+        (None, None) => None,
+        _ => return Err("a position needs both `x` and `y`".to_string()),
+    };
+
+    let (x, y) = position.unwrap_or_default();
+    let key = app
+        .add_device(name)
+        .kind(row.parse_enum("kind")?.unwrap_or_default())
+        .platform(row.parse_enum("platform")?)
+        .role(row.parse_enum("role")?)
+        .position(x, y)
+        .finish();
+    Ok((key, position.is_some()))
+}
+
+fn add_link(app: &mut App, keys: &HashMap<String, DefaultKey>, row: &Row) -> Result<(), String> {
+    let device = |column| {
+        let name = row.required(column)?;
+        keys.get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown device `{name}`"))
+    };
+    let (r1, r2) = (device("r1")?, device("r2")?);
+    if r1 == r2 {
+        return Err("a device can't be linked to itself".to_string());
+    }
+    let ip = row.required("ip")?;
+    let subnet = IpNet::from_str(ip).map_err(|_| format!("invalid subnet `{ip}`"))?;
+    if subnet.prefix_len() == subnet.max_prefix_len() {
+        return Err(format!("the subnet `{ip}` has less than two addresses"));
+    }
+    let ospf_area = row.parse::<u16>("ospf")?;
+    let cost = row.parse::<u16>("cost")?;
+
+    let mut builder = app.add_link(r1, r2).subnet(ip);
+    if let Some(area) = ospf_area {
+        builder = builder.ospf_area(area);
+    }
+    if let Some(cost) = cost {
+        builder = builder.cost(cost);
+    }
+    builder.finish();
+    Ok(())
+}
+
+/// A line of a CSV file, with its fields by column
+struct Row<'a> {
+    file: &'a str,
+    line: usize,
+    fields: HashMap<&'a str, String>,
+}
+
+impl Row<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{}, line {}: {message}", self.file, self.line)
+    }
+
+    /// The field of the column, if it isn't empty
+    fn get(&self, column: &str) -> Option<&str> {
+        let field = self.fields.get(column)?;
+        Some(field.as_str()).filter(|field| !field.is_empty())
+    }
+
+    fn required(&self, column: &str) -> Result<&str, String> {
+        self.get(column)
+            .ok_or_else(|| format!("the `{column}` column is empty"))
+    }
+
+    fn parse<T: FromStr>(&self, column: &str) -> Result<Option<T>, String> {
+        let Some(field) = self.get(column) else {
+            return Ok(None);
+        };
+        let value = field
+            .parse()
+            .map_err(|_| format!("invalid `{column}` `{field}`"))?;
+        Ok(Some(value))
+    }
+
+    /// Parse a field with the names used by the YAML documents
+    fn parse_enum<T: DeserializeOwned>(&self, column: &str) -> Result<Option<T>, String> {
+        let Some(field) = self.get(column) else {
+            return Ok(None);
+        };
+        let value = serde_yaml::from_value(Value::String(field.to_string()))
+            .map_err(|_| format!("invalid `{column}` `{field}`"))?;
+        Ok(Some(value))
+    }
+}
+
+/// The rows of a CSV file, whose first line names the columns, skipping
+/// the blank lines and reporting the malformed ones
+fn rows<'a>(
+    file: &'a str,
+    text: &'a str,
+    known: &[&'a str],
+    errors: &mut Vec<String>,
+) -> Vec<Row<'a>> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let Some((_, header)) = lines.next() else {
+        errors.push(format!("{file}: missing the line naming the columns"));
+        return vec![];
+    };
+
+    let header = match split(header) {
+        Ok(header) => header,
+        Err(e) => {
+            errors.push(format!("{file}, line 1: {e}"));
+            return vec![];
+        }
+    };
+    let mut columns = vec![];
+    for column in &header {
+        match known.iter().find(|&known| known == column) {
+            Some(&column) => columns.push(column),
+            None => errors.push(format!("{file}, line 1: unknown column `{column}`")),
+        }
+    }
+    if columns.len() != header.len() {
+        return vec![];
+    }
+
+    let mut rows = vec![];
+    for (line, text) in lines.filter(|(_, line)| !line.trim().is_empty()) {
+        let fields = match split(text) {
+            Ok(fields) if fields.len() <= columns.len() => fields,
+            Ok(_) => {
+                errors.push(format!("{file}, line {line}: too many fields"));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("{file}, line {line}: {e}"));
+                continue;
+            }
+        };
+        let fields = columns.iter().copied().zip(fields).collect();
+        rows.push(Row { file, line, fields });
+    }
+    rows
+}
+
+/// Split a line into its fields, which are trimmed unless quoted.
+/// A quoted field can contain commas, and quotes written twice
+fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    loop {
+        while chars.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unterminated quoted field".to_string()),
+                }
+            }
+            while chars.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+            if !matches!(chars.peek(), None | Some(',')) {
+                return Err("unexpected text after a quoted field".to_string());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
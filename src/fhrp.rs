@@ -0,0 +1,95 @@
+//! First-hop redundancy protocols, which share a virtual gateway
+//! address between the routers of a LAN
+
+use std::fmt::Write;
+
+use crate::{DeviceContext, DirectedLink, FhrpProtocol, FhrpTrack, GlbpLoadBalancing};
+
+/// Write the track objects used by VRRP and GLBP, which can't track interfaces directly
+///
+/// Every object has the number of the group it is used by
+pub(crate) fn write_track_objects(ctx: &DeviceContext, res: &mut String) {
+    let mut any = false;
+    for link in &ctx.links {
+        let (Some(fhrp), Some(track)) = (&link.fhrp, &link.close_options.fhrp_track) else {
+            continue;
+        };
+        if fhrp.protocol != FhrpProtocol::Hsrp {
+            let tracked = tracked_iface(ctx, track);
+            writeln!(
+                res,
+                "track {} interface {tracked} line-protocol",
+                fhrp.group
+            )
+            .unwrap();
+            any = true;
+        }
+    }
+    if any {
+        res.push('\n');
+    }
+}
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    let Some(fhrp) = &link.fhrp else {
+        return;
+    };
+    let group = fhrp.group;
+    let options = &link.close_options;
+    let cmd = match fhrp.protocol {
+        FhrpProtocol::Hsrp => "standby",
+        FhrpProtocol::Vrrp => "vrrp",
+        FhrpProtocol::Glbp => "glbp",
+    };
+
+    writeln!(res, "   {cmd} {group} ip {}", fhrp.virtual_ip).unwrap();
+    if let Some(priority) = options.fhrp_priority {
+        writeln!(res, "   {cmd} {group} priority {priority}").unwrap();
+    }
+    if options.fhrp_preempt {
+        writeln!(res, "   {cmd} {group} preempt").unwrap();
+    }
+    if fhrp.protocol == FhrpProtocol::Glbp {
+        if let Some(load_balancing) = fhrp.load_balancing {
+            let mode = match load_balancing {
+                GlbpLoadBalancing::RoundRobin => "round-robin",
+                GlbpLoadBalancing::Weighted => "weighted",
+                GlbpLoadBalancing::HostDependent => "host-dependent",
+            };
+            writeln!(res, "   glbp {group} load-balancing {mode}").unwrap();
+        }
+        if let Some(weighting) = options.glbp_weighting {
+            writeln!(res, "   glbp {group} weighting {weighting}").unwrap();
+        }
+    }
+    if let Some(track) = &options.fhrp_track {
+        let decrement = track.decrement;
+        match fhrp.protocol {
+            FhrpProtocol::Hsrp => {
+                let tracked = tracked_iface(ctx, track);
+                writeln!(res, "   standby {group} track {tracked} {decrement}").unwrap();
+            }
+            FhrpProtocol::Vrrp => {
+                writeln!(res, "   vrrp {group} track {group} decrement {decrement}").unwrap();
+            }
+            FhrpProtocol::Glbp => {
+                writeln!(
+                    res,
+                    "   glbp {group} weighting track {group} decrement {decrement}"
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// The name of the interface a device tracks
+fn tracked_iface(ctx: &DeviceContext, track: &FhrpTrack) -> String {
+    let tracked = ctx
+        .links
+        .iter()
+        .find(|other| ctx.app.devices[other.far_key].name == track.towards)
+        .unwrap_or_else(|| panic!("No link towards `{}` to track", track.towards));
+
+    tracked.iface_name()
+}
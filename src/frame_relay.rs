@@ -0,0 +1,136 @@
+//! Frame Relay PVCs between a hub router and its spokes, and the
+//! clouds switching them
+
+use std::fmt::Write;
+
+use ipnet::IpNet;
+use slotmap::DefaultKey;
+
+use crate::{
+    protocols, write_address, write_shutdown, App, DeviceContext, DeviceKind, DirectedLink,
+    InterfaceType,
+};
+
+/// Whether the link is a Frame Relay PVC, whose interfaces are written by `write_interfaces`
+pub(crate) fn is_pvc(link: &DirectedLink) -> bool {
+    matches!(link.interface_type, InterfaceType::FrameRelay(_))
+}
+
+/// Write the serial interfaces connected to Frame Relay clouds,
+/// with the point-to-point subinterfaces of the PVCs of a hub
+pub(crate) fn write_interfaces(ctx: &DeviceContext, res: &mut String) {
+    let mut pvcs: Vec<_> = ctx
+        .links
+        .iter()
+        .filter_map(|link| match link.interface_type {
+            InterfaceType::FrameRelay(pvc) => Some((link, pvc)),
+            _ => None,
+        })
+        .collect();
+    pvcs.sort_by_key(|(link, pvc)| (link.close_iface, pvc.hub_dlci));
+
+    let mut last_iface = None;
+    for (link, pvc) in pvcs {
+        let port_type = link.close_options.port_type;
+        let iface = ctx
+            .app
+            .port_name(ctx.key, link.interface_type, port_type, link.close_iface);
+        let cloud = &ctx.app.devices[pvc.cloud];
+        if pvc.hub == ctx.key {
+            if last_iface != Some(link.close_iface) {
+                last_iface = Some(link.close_iface);
+                writeln!(res, "interface {iface}").unwrap();
+                let port = cloud.kind.iface_name(pvc.hub_port);
+                writeln!(res, "   description Link to {} ({port})", cloud.name).unwrap();
+                res.push_str("   encapsulation frame-relay\n");
+                write_shutdown(res, link);
+                res.push_str("exit\n\n");
+            }
+            writeln!(res, "interface {} point-to-point", link.iface_name()).unwrap();
+            ctx.write_description(res, link, link.far_iface);
+            write_address(res, link.close_ip);
+            writeln!(res, "   frame-relay interface-dlci {}", pvc.hub_dlci).unwrap();
+            write_protocols(ctx, res, link);
+            res.push_str("exit\n\n");
+        } else {
+            writeln!(res, "interface {iface}").unwrap();
+            if let Some(description) = &link.close_options.description {
+                writeln!(res, "   description {description}").unwrap();
+            } else {
+                let port = cloud.kind.iface_name(pvc.spoke_port);
+                writeln!(res, "   description Link to {} ({port})", cloud.name).unwrap();
+            }
+            res.push_str("   encapsulation frame-relay\n");
+            write_address(res, link.close_ip);
+            let protocol = match link.far_ip {
+                IpNet::V4(_) => "ip",
+                IpNet::V6(_) => "ipv6",
+            };
+            writeln!(
+                res,
+                "   frame-relay map {protocol} {} {} broadcast",
+                link.far_ip.addr(),
+                pvc.spoke_dlci,
+            )
+            .unwrap();
+            write_protocols(ctx, res, link);
+            write_shutdown(res, link);
+            res.push_str("exit\n\n");
+        }
+    }
+}
+
+fn write_protocols(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    protocols::rip::write_interface(ctx, res, link);
+    protocols::ospf::write_interface(ctx, res, link);
+    protocols::eigrp::write_interface(ctx, res, link);
+    protocols::isis::write_interface(ctx, res, link);
+}
+
+/// Write the DLCIs of the serial ports of a Frame Relay cloud,
+/// and the PVCs connecting them
+pub(crate) fn write_cloud(app: &App, cloud: DefaultKey) -> String {
+    let mut pvcs = Vec::new();
+    for link in app.links.values() {
+        let (r1, r2) = link.ends;
+        if let InterfaceType::FrameRelay(pvc) = link.interface_type {
+            if pvc.cloud == cloud {
+                let spoke = if r1 == pvc.hub { r2 } else { r1 };
+                pvcs.push((pvc, &app.devices[pvc.hub].name, &app.devices[spoke].name));
+            }
+        }
+    }
+    pvcs.sort_by_key(|(pvc, ..)| pvc.spoke_port);
+
+    let mut dlcis = Vec::new();
+    for (pvc, hub, spoke) in &pvcs {
+        dlcis.push((pvc.hub_port, pvc.hub_dlci, format!("{hub}-{spoke}")));
+        dlcis.push((pvc.spoke_port, pvc.spoke_dlci, format!("{spoke}-{hub}")));
+    }
+    dlcis.sort();
+
+    let mut res = String::new();
+    let port_name = |port| DeviceKind::FrameRelayCloud.iface_name(port);
+    let mut last_port = None;
+    for (port, dlci, name) in &dlcis {
+        if last_port != Some(port) {
+            last_port = Some(port);
+            writeln!(res, "{}", port_name(*port)).unwrap();
+        }
+        writeln!(res, "   DLCI {dlci}: {name}").unwrap();
+    }
+
+    if !pvcs.is_empty() {
+        res.push_str("\nFrame Relay\n");
+    }
+    for (pvc, hub, spoke) in &pvcs {
+        writeln!(
+            res,
+            "   {} {hub}-{spoke} <-> {} {spoke}-{hub}",
+            port_name(pvc.hub_port),
+            port_name(pvc.spoke_port),
+        )
+        .unwrap();
+    }
+    res
+}
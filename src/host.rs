@@ -0,0 +1,99 @@
+//! The settings of the end hosts, which are entered in their desktop
+//! rather than in a CLI
+
+use std::{fmt::Write, net::IpAddr};
+
+use ipnet::IpNet;
+
+use crate::{App, DeviceContext, Services};
+
+pub(crate) fn write_settings(ctx: &DeviceContext) -> String {
+    let mut res = String::new();
+    let link = match ctx.links.as_slice() {
+        [] => return res,
+        [link] => link,
+        _ => panic!("Host `{}` can only have one link", ctx.device.name),
+    };
+
+    let ip = link.close_ip;
+    match ip {
+        IpNet::V4(ip) => {
+            writeln!(res, "IPv4 Address: {}", ip.addr()).unwrap();
+            writeln!(res, "Subnet Mask: {}", ip.netmask()).unwrap();
+        }
+        IpNet::V6(ip) => writeln!(res, "IPv6 Address: {ip}").unwrap(),
+    }
+    if let Some(ipv6) = link.close_ipv6 {
+        writeln!(res, "IPv6 Address: {ipv6}").unwrap();
+    }
+    if let Some(gateway) = ctx.device.default_gateway.or_else(|| gateway(ctx.app, ip)) {
+        writeln!(res, "Default Gateway: {gateway}").unwrap();
+    }
+    if let Some(dns_server) = ctx.device.dns_server {
+        writeln!(res, "DNS Server: {dns_server}").unwrap();
+    }
+    write_services(&mut res, &ctx.device.services);
+    res
+}
+
+fn write_services(res: &mut String, services: &Services) {
+    if *services == Services::default() {
+        return;
+    }
+
+    res.push_str("\nServices\n");
+    if services.http {
+        res.push_str("   HTTP: On\n");
+    }
+    if services.tftp {
+        res.push_str("   TFTP: On\n");
+    }
+    if !services.dns_records.is_empty() {
+        res.push_str("   DNS: On\n");
+        for record in &services.dns_records {
+            writeln!(res, "      {} A {}", record.name, record.address).unwrap();
+        }
+    }
+    if !services.dhcp_pools.is_empty() {
+        res.push_str("   DHCP: On\n");
+        for pool in &services.dhcp_pools {
+            writeln!(res, "      Pool {}", pool.name).unwrap();
+            if let Some(gateway) = pool.default_gateway {
+                writeln!(res, "         Default Gateway: {gateway}").unwrap();
+            }
+            if let Some(dns_server) = pool.dns_server {
+                writeln!(res, "         DNS Server: {dns_server}").unwrap();
+            }
+            writeln!(res, "         Start IP Address: {}", pool.start.addr()).unwrap();
+            writeln!(res, "         Subnet Mask: {}", pool.start.netmask()).unwrap();
+            writeln!(res, "         Maximum Number of Users: {}", pool.max_users).unwrap();
+        }
+    }
+}
+
+/// The address of the first routing device in the subnet of `ip`
+fn gateway(app: &App, ip: IpNet) -> Option<IpAddr> {
+    let in_subnet = |other: &IpNet| other.trunc() == ip.trunc() && other.addr() != ip.addr();
+
+    for (key, device) in &app.devices {
+        if !device.kind.routes() {
+            continue;
+        }
+        for link in app.links_of(key) {
+            if in_subnet(&link.close_ip) {
+                return Some(link.close_ip.addr());
+            }
+            for (_, sub_ip) in &link.close_subinterfaces {
+                if in_subnet(sub_ip) {
+                    return Some(sub_ip.addr());
+                }
+            }
+        }
+        for svi in &device.svis {
+            if in_subnet(&svi.ip) {
+                return Some(svi.ip.addr());
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,250 @@
+//! A strict parser for JSON topology files, which turns them into the same
+//! values as the equivalent YAML
+//!
+//! serde_yaml would also accept the YAML which isn't JSON, so the syntax is
+//! checked here instead
+
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+
+use crate::document_key;
+
+/// Deserialize a JSON document, reporting the line of the first syntax error
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    let mut parser = Parser::new(text);
+    parser.skip_spaces();
+    let value = parser.value()?;
+    parser.skip_spaces();
+    if parser.peek().is_some() {
+        return Err(parser.error("expected the end of the document"));
+    }
+    serde_yaml::from_value(value).map_err(|e| e.to_string())
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Whether the text continues with `s`
+    fn peek_str(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {message}", self.line)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{c}`")))
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+            self.next();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some('-' | '0'..='9') => self.number(),
+            _ => {
+                for (word, value) in [
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                    ("null", Value::Null),
+                ] {
+                    if self.peek_str(word) {
+                        self.pos += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+
+    /// A number, which unlike the ones of Rust can't have leading zeros,
+    /// a leading `+`, or a dot without digits on both sides
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.next();
+        }
+        if self.peek() == Some('0') {
+            self.next();
+        } else {
+            self.digits()?;
+        }
+        if self.peek() == Some('.') {
+            self.next();
+            self.digits()?;
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.next();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.next();
+            }
+            self.digits()?;
+        }
+
+        let number: String = self.chars[start..self.pos].iter().collect();
+        if let Ok(number) = number.parse::<i64>() {
+            Ok(Value::Number(number.into()))
+        } else {
+            // Always valid, since the syntax was checked above
+            Ok(Value::Number(number.parse::<f64>().unwrap().into()))
+        }
+    }
+
+    fn digits(&mut self) -> Result<(), String> {
+        if !matches!(self.peek(), Some('0'..='9')) {
+            return Err(self.error("expected a digit"));
+        }
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.next();
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut res = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(res),
+                Some('\\') => res.push(self.escape()?),
+                Some(c) if c < ' ' => return Err(self.error("control character in a string")),
+                Some(c) => res.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// The character escaped by a backslash
+    fn escape(&mut self) -> Result<char, String> {
+        let c = match self.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some(c @ ('"' | '\\' | '/')) => c,
+            Some('u') => {
+                let high = self.code_unit()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    // A character outside the basic plane, as a surrogate pair
+                    if !self.peek_str("\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.code_unit()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))?
+            }
+            _ => return Err(self.error("invalid escape sequence")),
+        };
+        Ok(c)
+    }
+
+    /// The four hexadecimal digits of a `\u` escape
+    fn code_unit(&mut self) -> Result<u32, String> {
+        let mut code = String::new();
+        for _ in 0..4 {
+            code.extend(self.next());
+        }
+        u32::from_str_radix(&code, 16)
+            .map_err(|_| self.error(&format!("invalid unicode escape `{code}`")))
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        self.skip_spaces();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Value::Sequence(values));
+        }
+        loop {
+            self.skip_spaces();
+            values.push(self.value()?);
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Sequence(values)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut object = Mapping::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Value::Mapping(object));
+        }
+        loop {
+            self.skip_spaces();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a key"));
+            }
+            let name = self.string()?;
+            self.skip_spaces();
+            self.expect(':')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            let key = document_key(name.clone());
+            if object.contains_key(&key) {
+                return Err(self.error(&format!("the key `{name}` is defined twice")));
+            }
+            object.insert(key, value);
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Mapping(object)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
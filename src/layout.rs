@@ -0,0 +1,62 @@
+//! Placement of the devices on the workspace, for the devices whose
+//! position wasn't given by hand
+
+use std::collections::{BTreeSet, HashMap};
+
+use slotmap::DefaultKey;
+
+use crate::App;
+
+/// The distance between two consecutive layers
+const LAYER_SPACING: f32 = 200.0;
+/// The distance between two devices in the same layer
+const DEVICE_SPACING: f32 = 100.0;
+
+/// Place the devices in layers, by their distance in links from the first
+/// device of their partition, so that links mostly run between neighbouring layers
+///
+/// The partitions are stacked one below the other
+pub(crate) fn layered(app: &App) -> HashMap<DefaultKey, (f32, f32)> {
+    let mut partitions = app.partitions();
+    // Frame Relay clouds aren't part of any partition
+    let placed: BTreeSet<_> = partitions.iter().flatten().copied().collect();
+    for key in app.devices.keys() {
+        if !placed.contains(&key) {
+            partitions.push(vec![key]);
+        }
+    }
+
+    let mut positions = HashMap::new();
+    let mut top = 0.0;
+    for partition in partitions {
+        let mut layers: Vec<Vec<DefaultKey>> = vec![vec![partition[0]]];
+        let mut visited = BTreeSet::from([partition[0]]);
+        loop {
+            let mut next: Vec<_> = layers
+                .last()
+                .unwrap()
+                .iter()
+                .flat_map(|&key| app.neighbors(key))
+                .filter(|&key| visited.insert(key))
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            next.sort();
+            layers.push(next);
+        }
+
+        let height = layers.iter().map(Vec::len).max().unwrap();
+        for (depth, layer) in layers.iter().enumerate() {
+            // Center the layer on the tallest one
+            let offset = (height - layer.len()) as f32 * DEVICE_SPACING / 2.0;
+            for (i, &key) in layer.iter().enumerate() {
+                let x = depth as f32 * LAYER_SPACING;
+                let y = top + offset + i as f32 * DEVICE_SPACING;
+                positions.insert(key, (x, y));
+            }
+        }
+        top += height as f32 * DEVICE_SPACING;
+    }
+    positions
+}
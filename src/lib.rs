@@ -1,8 +1,39 @@
-use std::{collections::BTreeMap, collections::HashMap, fmt::Write, net::IpAddr, str::FromStr};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Write,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::Deref,
+    str::FromStr,
+};
 
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use serde::Deserialize;
-use slotmap::{DefaultKey, SlotMap};
+use slotmap::{new_key_type, DefaultKey, SlotMap};
+
+use allocator::AddressPool;
+
+mod acl;
+mod allocator;
+mod autoconfig;
+pub mod csv;
+mod fhrp;
+mod frame_relay;
+mod host;
+pub mod json;
+mod layout;
+mod management;
+mod protocols;
+mod qos;
+mod random;
+mod switching;
+pub mod toml;
+mod vpn;
+
+new_key_type! {
+    /// The key of a `Link`, which tells apart the parallel links between two devices
+    pub struct LinkKey;
+}
 
 /// A generator of commands for Packet Tracer
 ///
@@ -11,8 +42,30 @@ use slotmap::{DefaultKey, SlotMap};
 #[derive(Default)]
 pub struct App {
     pub devices: SlotMap<DefaultKey, Device>,
-    pub links: HashMap<(DefaultKey, DefaultKey), Link>,
+    links: SlotMap<LinkKey, Link>,
+    /// The links between every pair of devices, ordered, from the oldest
+    adjacency: HashMap<(DefaultKey, DefaultKey), Vec<LinkKey>>,
     pub rip_enabled: Vec<DefaultKey>,
+    /// The type of every OSPF area that isn't a normal area
+    pub ospf_areas: HashMap<u16, AreaType>,
+    /// The OSPF areas using MD5 authentication
+    pub ospf_auth_areas: BTreeSet<u16>,
+    /// The MD5 key shared by all the authenticated OSPF interfaces
+    pub ospf_md5_key: Option<String>,
+    /// The passwords of the devices which don't set their own
+    pub default_passwords: Passwords,
+    /// The MOTD banner of the devices which don't set their own
+    pub default_banner: Option<String>,
+    pub vpns: Vec<Vpn>,
+    /// The LANs shared by more than two devices through a switch
+    pub segments: Vec<Segment>,
+    /// The groups of devices sharing the defaults of a site
+    sites: Vec<Site>,
+    /// The block the subnets of the links are allocated from, if any
+    address_pool: Option<AddressPool>,
+    /// The subnets allocated from the address pool or planned with VLSM,
+    /// with what they are allocated to
+    allocations: Vec<(String, IpNet)>,
 }
 
 impl App {
@@ -20,30 +73,105 @@ impl App {
     pub fn new() -> Self {
         App {
             devices: SlotMap::new(),
-            links: HashMap::new(),
+            links: SlotMap::with_key(),
+            adjacency: HashMap::new(),
             rip_enabled: vec![],
+            ospf_areas: HashMap::new(),
+            ospf_auth_areas: BTreeSet::new(),
+            ospf_md5_key: None,
+            default_passwords: Passwords::default(),
+            default_banner: None,
+            vpns: vec![],
+            segments: vec![],
+            sites: vec![],
+            address_pool: None,
+            allocations: vec![],
         }
     }
 
     /// Register a `Device`
-    pub fn add_device(&mut self, name: &str) -> DeviceBuilder {
+    pub fn add_device(&mut self, name: &str) -> DeviceBuilder<'_> {
         DeviceBuilder {
             app: self,
-            name: name.to_string(),
-            x: 0.,
-            y: 0.,
-            redistributions: Redistributions { ospf_to_rip: false },
+            device: Device {
+                name: name.to_string(),
+                ..Default::default()
+            },
         }
     }
 
     /// Retrieve a `Device` by name
     pub fn get_device<'a>(&'a mut self, name: &str) -> Option<&'a mut Device> {
-        for dev in self.devices.values_mut() {
-            if dev.name == name {
-                return Some(dev);
+        self.devices.values_mut().find(|dev| dev.name == name)
+    }
+
+    /// Retrieve a `Device` by key
+    pub fn device(&self, key: DefaultKey) -> Option<&Device> {
+        self.devices.get(key)
+    }
+
+    /// Retrieve a `Device` by key, to modify it
+    pub fn device_mut(&mut self, key: DefaultKey) -> Option<&mut Device> {
+        self.devices.get_mut(key)
+    }
+
+    /// Find the key of the device called `name`
+    pub fn find_key_by_name(&self, name: &str) -> Option<DefaultKey> {
+        self.devices
+            .iter()
+            .find(|(_, dev)| dev.name == name)
+            .map(|(key, _)| key)
+    }
+
+    /// Merge `defaults` into the settings of every device with the given role
+    pub fn apply_role_defaults(&mut self, role: Role, defaults: &DeviceDefaults) {
+        for key in self.devices_with_role(role) {
+            defaults.merge_into(&mut self.devices[key]);
+        }
+    }
+
+    /// Merge `defaults` into the settings of every device tagged with `tag`
+    pub fn apply_tag_defaults(&mut self, tag: &str, defaults: &DeviceDefaults) {
+        for key in self.devices_tagged(tag) {
+            defaults.merge_into(&mut self.devices[key]);
+        }
+    }
+
+    /// The keys of the devices with the given role
+    pub fn devices_with_role(&self, role: Role) -> Vec<DefaultKey> {
+        self.devices
+            .iter()
+            .filter(|(_, dev)| dev.role == Some(role))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// The keys of the devices tagged with `tag`
+    pub fn devices_tagged(&self, tag: &str) -> Vec<DefaultKey> {
+        self.devices
+            .iter()
+            .filter(|(_, dev)| dev.tags.iter().any(|other| other == tag))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// The address other devices can reach the device `name` at: the one of
+    /// its first interface, if the device exists and has one
+    pub(crate) fn device_address(&self, name: &str) -> Option<IpAddr> {
+        let key = self.find_key_by_name(name)?;
+        let link = self.links_of(key).into_iter().next()?;
+        Some(link.close_ip.addr())
+    }
+
+    /// Why the device `name` can't be used as a server, if it can't
+    fn unreachable_server(&self, name: &str) -> Option<String> {
+        match self.find_key_by_name(name) {
+            None => Some(format!("there is no device named `{name}`")),
+            Some(key) if self.links_of(key).is_empty() => {
+                Some(format!("`{name}` has no links to be reached at"))
             }
+            Some(_) => None,
         }
-        None
     }
 
     pub fn get_directed_link(
@@ -53,277 +181,6897 @@ impl App {
     ) -> Option<DirectedLink> {
         assert_ne!(close_key, far_key);
 
-        // Order `r_close` and `r_far`
-        let (r1_close, key) = if close_key < far_key {
-            (true, (close_key, far_key))
-        } else {
-            (false, (far_key, close_key))
+        let key = self.link_between(close_key, far_key)?;
+        Some(self.directed_link(key, close_key))
+    }
+
+    /// The name of the `iface`-th network interface of `device`, which is
+    /// a port of its platform if it has one, or else numbered by its numbering
+    ///
+    /// The ports of each type are taken in order, as the interfaces using them were added
+    fn port_name(
+        &self,
+        device: DefaultKey,
+        interface_type: InterfaceType,
+        port_type: Option<PortType>,
+        iface: u8,
+    ) -> String {
+        let Device {
+            name,
+            kind,
+            platform,
+            numbering,
+            ..
+        } = &self.devices[device];
+        let Some(platform) = *platform else {
+            let numbering = numbering.clone().unwrap_or_else(|| kind.numbering());
+            return interface_type.iface_name(*kind, &numbering, port_type, iface);
         };
+        let port_type = platform.port_type(interface_type, port_type);
+        if port_type == PortType::Loopback {
+            return format!("Loopback{iface}");
+        }
+
+        // The PVCs of a Frame Relay hub share an interface, so it's counted once
+        let mut previous = BTreeSet::new();
+        for link in self.links.values() {
+            let (r1, r2) = link.ends;
+            let sides = [
+                (r1, &link.r1_options, link.r1_iface, &link.r1_members),
+                (r2, &link.r2_options, link.r2_iface, &link.r2_members),
+            ];
+            for (key, options, close_iface, members) in sides {
+                if key != device
+                    || platform.port_type(link.interface_type, options.port_type) != port_type
+                {
+                    continue;
+                }
+                let ifaces = std::iter::once(close_iface).chain(members.iter().copied());
+                previous.extend(ifaces.filter(|&other| other < iface));
+            }
+        }
+        let index = previous.len() as u8;
+        platform
+            .port_name(port_type, index)
+            .unwrap_or_else(|| panic!("{name} has more {port_type:?} ports than a {platform}"))
+    }
+
+    /// The link `key` as seen from its side at `close_key`
+    fn directed_link(&self, key: LinkKey, close_key: DefaultKey) -> DirectedLink {
+        let link = &self.links[key];
+        let (r1, r2) = link.ends;
+        let r1_close = r1 == close_key;
+        let far_key = if r1_close { r2 } else { r1 };
 
-        self.links.get(&key).map(|link| DirectedLink {
+        let r1_ip = self.interface_ip(r1, &link.r1_options, link.r1);
+        let r2_ip = self.interface_ip(r2, &link.r2_options, link.r2);
+        DirectedLink {
             close_key,
             far_key,
-            close_ip: if r1_close { link.r1 } else { link.r2 },
-            far_ip: if r1_close { link.r2 } else { link.r1 },
+            close_ip: if r1_close { r1_ip } else { r2_ip },
+            far_ip: if r1_close { r2_ip } else { r1_ip },
+            close_ipv6: link.ipv6.map(|(r1, r2)| if r1_close { r1 } else { r2 }),
+            far_ipv6: link.ipv6.map(|(r1, r2)| if r1_close { r2 } else { r1 }),
             close_iface: if r1_close {
                 link.r1_iface
             } else {
                 link.r2_iface
             },
+            far_iface: if r1_close {
+                link.r2_iface
+            } else {
+                link.r1_iface
+            },
+            close_options: if r1_close {
+                link.r1_options.clone()
+            } else {
+                link.r2_options.clone()
+            },
+            close_port: if r1_close {
+                self.port_name(
+                    r1,
+                    link.interface_type,
+                    link.r1_options.port_type,
+                    link.r1_iface,
+                )
+            } else {
+                self.port_name(
+                    r2,
+                    link.interface_type,
+                    link.r2_options.port_type,
+                    link.r2_iface,
+                )
+            },
+            far_port_type: if r1_close {
+                link.r2_options.port_type
+            } else {
+                link.r1_options.port_type
+            },
+            close_subinterfaces: link
+                .sublinks
+                .iter()
+                .map(|sublink| (sublink.vlan, if r1_close { sublink.r1 } else { sublink.r2 }))
+                .collect(),
+            close_members: if r1_close {
+                link.r1_members.clone()
+            } else {
+                link.r2_members.clone()
+            },
+            far_members: if r1_close {
+                link.r2_members.clone()
+            } else {
+                link.r1_members.clone()
+            },
+            far_routers: self
+                .segments
+                .iter()
+                .find(|segment| segment.switch == far_key && segment.members.contains(&close_key))
+                .map_or_else(
+                    || vec![far_key],
+                    |segment| {
+                        let members = segment.members.iter().copied();
+                        members.filter(|&member| member != close_key).collect()
+                    },
+                ),
+            channel_group: link.channel_group,
+            interface_type: link.interface_type,
             ospf_area: link.ospf_area,
-        })
+            ospf_cost: link.ospf_cost,
+            ospf_auth: link.ospf_auth,
+            eigrp: link.eigrp,
+            bgp: link.bgp,
+            isis: link.isis,
+            pim: link.pim,
+            fhrp: link.fhrp.clone(),
+            bfd: link.bfd,
+            ospf_network_type: link.ospf_network_type,
+            ospf_process: link.ospf_process,
+            trunk: link.trunk.clone(),
+            access_vlan: link.access_vlan,
+            voice_vlan: link.voice_vlan,
+            ppp: link.ppp.clone(),
+            mtu: link.mtu,
+            bandwidth: link.bandwidth,
+            delay: link.delay,
+            eui64: link.eui64,
+        }
     }
 
-    /// Connect two devices by name
-    ///
-    /// If the two devices already share a link, then it gets updated
-    /// to use the new ip. Otherwise, a new link is created
-    pub fn link(&mut self, r1: DefaultKey, r2: DefaultKey, ip: &str, ospf_area: Option<u16>) {
-        let ip = IpNet::from_str(ip).expect(&format!("Failed to parse ip: {ip}"));
+    /// The address of one side of a link, which is the one of a loopback
+    /// if the interface is unnumbered. A missing loopback is reported by
+    /// `validate`, and leaves the address of the link
+    fn interface_ip(&self, key: DefaultKey, options: &InterfaceOptions, ip: IpNet) -> IpNet {
+        options
+            .unnumbered
+            .and_then(|loopback| self.devices[key].loopbacks.get(loopback as usize))
+            .copied()
+            .unwrap_or(ip)
+    }
 
+    /// Retrieve the options of the interface of `close_key` connected to `far_key`
+    pub fn interface_mut(
+        &mut self,
+        close_key: DefaultKey,
+        far_key: DefaultKey,
+    ) -> Option<&mut InterfaceOptions> {
+        assert_ne!(close_key, far_key);
+
+        let key = self.link_between(close_key, far_key)?;
+        let link = &mut self.links[key];
+        if link.ends.0 == close_key {
+            Some(&mut link.r1_options)
+        } else {
+            Some(&mut link.r2_options)
+        }
+    }
+
+    /// Connect two devices with a new link, whose options are set through
+    /// the returned builder
+    ///
+    /// If the two devices already share a link, the new one runs parallel to it
+    pub fn add_link(&mut self, r1: DefaultKey, r2: DefaultKey) -> LinkBuilder<'_> {
         assert_ne!(r1, r2);
-        assert!(ip.hosts().count() >= 2);
 
         // Order `r1` and `r2`
         let (r1, r2) = if r1 < r2 { (r1, r2) } else { (r2, r1) };
 
-        let link = self.links.entry((r1, r2)).or_default();
-        let mut hosts = ip.hosts();
+        let key = self.connect(r1, r2);
+        LinkBuilder {
+            app: self,
+            key,
+            addresses: None,
+            chosen: (None, None),
+        }
+    }
 
-        link.r1 = to_ipnet(hosts.next().unwrap(), ip.prefix_len());
-        link.r2 = to_ipnet(hosts.next().unwrap(), ip.prefix_len());
-        link.ospf_area = ospf_area;
+    /// Like `add_link`, but update the last link between the two devices
+    /// if they are already connected, giving it new interfaces
+    fn update_link(&mut self, r1: DefaultKey, r2: DefaultKey) -> LinkBuilder<'_> {
+        let Some(key) = self.link_between(r1, r2) else {
+            return self.add_link(r1, r2);
+        };
+        let link = &mut self.links[key];
+        let (r1, r2) = link.ends;
         link.r1_iface = self.devices[r1].next_iface;
         link.r2_iface = self.devices[r2].next_iface;
-
         self.devices[r1].next_iface += 1;
         self.devices[r2].next_iface += 1;
+        LinkBuilder {
+            app: self,
+            key,
+            addresses: None,
+            chosen: (None, None),
+        }
     }
 
-    /// Disconnect the two devices if they are connected
-    pub fn unlink(&mut self, r1: DefaultKey, r2: DefaultKey) {
-        assert_ne!(r1, r2);
-
-        // Order `r1` and `r2`
-        let key = if r1 < r2 { (r1, r2) } else { (r2, r1) };
-
-        self.links.remove(&key);
+    /// Connect two devices by name
+    ///
+    /// If the two devices already share a link, then it gets updated
+    /// to use the new ip. Otherwise, a new link is created.
+    ///
+    /// The returned `Link` can be used to set further per-link options
+    pub fn link(
+        &mut self,
+        r1: DefaultKey,
+        r2: DefaultKey,
+        ip: &str,
+        ospf_area: Option<u16>,
+    ) -> &mut Link {
+        let link = self.update_link(r1, r2).subnet(ip).finish();
+        link.ospf_area = ospf_area;
+        link
     }
 
-    /// Generate the commands to print to the user
-    pub fn to_commands(&self) -> BTreeMap<String, String> {
-        let mut map = BTreeMap::new();
+    /// Carve the subnets given by `allocate_subnet` out of `pool`,
+    /// each with a prefix of `prefix_len` bits
+    pub fn set_address_pool(&mut self, pool: &str, prefix_len: u8) {
+        let pool = IpNet::from_str(pool).unwrap_or_else(|_| panic!("Failed to parse ip: {pool}"));
+        let mut pool = AddressPool::new(pool, prefix_len);
+        for link in self.links.values() {
+            pool.reserve(link.r1);
+        }
+        self.address_pool = Some(pool);
+    }
 
-        for (close_key, device) in &self.devices {
-            let mut res = String::from("enable\nconfigure terminal\n\n");
+    /// Keep the address pools of the network and of the sites from allocating `subnet`
+    fn reserve(&mut self, subnet: IpNet) {
+        let site_pools = self
+            .sites
+            .iter_mut()
+            .filter_map(|site| site.address_pool.as_mut());
+        for pool in self.address_pool.iter_mut().chain(site_pools) {
+            pool.reserve(subnet);
+        }
+    }
 
-            let directly_connected: Vec<DirectedLink> = self
-                .links
-                .iter()
-                .filter_map(|(&key, _)| {
-                    if key.0 == close_key {
-                        Some(key.1)
-                    } else if key.1 == close_key {
-                        Some(key.0)
-                    } else {
-                        None
-                    }
-                })
-                .map(|far_key| self.get_directed_link(close_key, far_key).unwrap())
-                .collect();
+    /// Keep the address pool from allocating a subnet which is assigned by hand
+    ///
+    /// The subnets of the links are reserved when they are created, so this
+    /// is only needed for the ones of links created after the allocation
+    pub fn reserve_subnet(&mut self, subnet: &str) {
+        let subnet =
+            IpNet::from_str(subnet).unwrap_or_else(|_| panic!("Failed to parse ip: {subnet}"));
+        self.reserve(subnet);
+    }
 
-            // Network interfaces
-            for link in &directly_connected {
-                writeln!(
-                    res,
-                    concat!(
-                        "interface GigabitEthernet {}/0\n",
-                        "   ip address {} {}\n",
-                        "   no shutdown\n",
-                        "exit\n",
-                    ),
-                    link.close_iface,
-                    link.close_ip.addr(),
-                    link.close_ip.netmask(),
-                )
-                .unwrap();
-            }
+    /// Take the next free subnet of the address pool, recording that
+    /// it's allocated to the link between `r1` and `r2`
+    ///
+    /// The links inside a site with its own address pool take their subnet from it
+    pub fn allocate_subnet(&mut self, r1: DefaultKey, r2: DefaultKey) -> IpNet {
+        let site_pool = self
+            .sites
+            .iter_mut()
+            .find(|site| site.members.contains(&r1) && site.members.contains(&r2))
+            .and_then(|site| site.address_pool.as_mut());
+        let subnet = site_pool
+            .or(self.address_pool.as_mut())
+            .expect("Subnets can only be allocated after setting an address pool")
+            .allocate()
+            .expect("The address pool is exhausted");
+        let (r1, r2) = (&self.devices[r1].name, &self.devices[r2].name);
+        self.allocations.push((format!("{r1} - {r2}"), subnet));
+        subnet
+    }
 
-            // RIP v2
-            res.push_str("router rip\n   version 2\n");
-            for link in &directly_connected {
-                if self.rip_enabled.contains(&link.far_key) {
-                    writeln!(res, "   network {}", link.far_ip.network()).unwrap();
-                }
+    /// Group devices into a site, whose defaults they share
+    pub fn add_site(&mut self, mut site: Site) {
+        for &member in &site.members {
+            if let Some(other) = self.site_of(member) {
+                let name = &self.devices[member].name;
+                panic!("{name} is already a member of the site {}", other.name);
             }
-            res.push_str("exit\n\n");
-
-            // OSPF
-            res.push_str("router ospf 1\n");
-            if device.redistributions.ospf_to_rip {
-                res.push_str("   redistribute rip subnets\n")
+        }
+        if let Some(pool) = &mut site.address_pool {
+            for link in self.links.values() {
+                pool.reserve(link.r1);
             }
-            for link in &directly_connected {
-                if let Some(ospf_area) = link.ospf_area {
-                    writeln!(
-                        res,
-                        "   network {} {} area {}",
-                        link.far_ip.network(),
-                        link.far_ip.hostmask(),
-                        ospf_area,
-                    )
-                    .unwrap();
-                }
+            if let Some(global) = &mut self.address_pool {
+                global.reserve(pool.block());
             }
-            res.push_str("exit\n\n");
-
-            res.push_str("\nexit\ndisable\n");
-            map.insert(device.name.clone(), res);
         }
+        self.sites.push(site);
+    }
 
-        map
+    pub fn sites(&self) -> &[Site] {
+        &self.sites
     }
-}
 
-/// A router
-#[derive(Default, Debug, PartialEq)]
-pub struct Device {
-    pub name: String,
-    pub x: f32,
-    pub y: f32,
-    pub redistributions: Redistributions,
-    next_iface: u8,
-}
+    /// The site the device belongs to, if any
+    pub fn site_of(&self, key: DefaultKey) -> Option<&Site> {
+        self.sites.iter().find(|site| site.members.contains(&key))
+    }
 
-/// A link between routers.
-///
-/// `r1` must always be less than `r2`
-#[derive(Default)]
-pub struct Link {
-    r1: IpNet,
-    r2: IpNet,
-    r1_iface: u8,
-    r2_iface: u8,
-    ospf_area: Option<u16>,
-}
+    /// Plan with VLSM the subnets of LAN segments, each given with its name
+    /// and number of hosts: each gets the smallest subnet of `block` which
+    /// fits its hosts, and is recorded in the allocation table
+    pub fn plan_vlsm(&mut self, block: &str, segments: &[(&str, u32)]) -> Vec<IpNet> {
+        let block =
+            IpNet::from_str(block).unwrap_or_else(|_| panic!("Failed to parse ip: {block}"));
+        let hosts: Vec<u32> = segments.iter().map(|&(_, hosts)| hosts).collect();
+        let subnets = allocator::plan_vlsm(block, &hosts)
+            .unwrap_or_else(|| panic!("The segments don't fit in {block}"));
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct DirectedLink {
-    close_key: DefaultKey,
-    far_key: DefaultKey,
-    close_ip: IpNet,
-    far_ip: IpNet,
-    close_iface: u8,
-    ospf_area: Option<u16>,
-}
+        for (&(name, hosts), &subnet) in segments.iter().zip(&subnets) {
+            self.reserve(subnet);
+            self.allocations
+                .push((format!("{name} ({hosts} hosts)"), subnet));
+        }
+        subnets
+    }
 
-/// Convert an `IpAddr` to an `IpNet` with the given prefix length
-fn to_ipnet(ip: IpAddr, cidr: u8) -> IpNet {
-    match ip {
-        IpAddr::V4(ipv4) => IpNet::V4(Ipv4Net::new(ipv4, cidr).unwrap()),
-        IpAddr::V6(ipv6) => IpNet::V6(Ipv6Net::new(ipv6, cidr).unwrap()),
+    /// The table of the subnets allocated from the address pool
+    /// or planned with VLSM, one per line
+    pub fn allocation_table(&self) -> String {
+        let mut res = String::new();
+        for (owner, subnet) in &self.allocations {
+            writeln!(res, "{owner}: {subnet}").unwrap();
+        }
+        res
     }
-}
 
-#[derive(Default, Debug, PartialEq, Deserialize)]
-pub struct Redistributions {
-    pub ospf_to_rip: bool,
-}
+    /// Connect two routers with a link which needs no subnet of its own,
+    /// as both its interfaces borrow the address of their router's first loopback
+    pub fn link_unnumbered(
+        &mut self,
+        r1: DefaultKey,
+        r2: DefaultKey,
+        ospf_area: Option<u16>,
+    ) -> &mut Link {
+        let link = self.update_link(r1, r2).finish();
+        link.ospf_area = ospf_area;
+        link
+    }
 
-pub struct DeviceBuilder<'a> {
-    app: &'a mut App,
+    /// Create a link between `r1` and `r2`, which must be ordered,
+    /// giving it a new interface on both sides
+    fn connect(&mut self, r1: DefaultKey, r2: DefaultKey) -> LinkKey {
+        let link = Link {
+            ends: (r1, r2),
+            r1_iface: self.devices[r1].next_iface,
+            r2_iface: self.devices[r2].next_iface,
+            ..Default::default()
+        };
+        self.devices[r1].next_iface += 1;
+        self.devices[r2].next_iface += 1;
+        self.insert_link(link)
+    }
 
-    name: String,
-    x: f32,
-    y: f32,
-    redistributions: Redistributions,
-}
+    fn insert_link(&mut self, link: Link) -> LinkKey {
+        let ends = link.ends;
+        let key = self.links.insert(link);
+        self.adjacency.entry(ends).or_default().push(key);
+        key
+    }
 
-impl DeviceBuilder<'_> {
-    pub fn name(self, name: String) -> Self {
-        Self { name, ..self }
+    /// The last link created between two devices, if they are connected
+    fn link_between(&self, r1: DefaultKey, r2: DefaultKey) -> Option<LinkKey> {
+        let ends = if r1 < r2 { (r1, r2) } else { (r2, r1) };
+        self.adjacency.get(&ends)?.last().copied()
     }
 
-    pub fn position(self, x: f32, y: f32) -> Self {
-        Self { x, y, ..self }
+    /// Set the type of an OSPF area
+    pub fn set_area_type(&mut self, area: u16, area_type: AreaType) {
+        self.ospf_areas.insert(area, area_type);
     }
 
-    pub fn redistribute_ospf_to_rip(mut self, b: bool) -> Self {
-        self.redistributions.ospf_to_rip = b;
-        self
+    /// Enable MD5 authentication on every interface of an OSPF area
+    pub fn authenticate_area(&mut self, area: u16) {
+        self.ospf_auth_areas.insert(area);
     }
 
-    pub fn finish(self) -> DefaultKey {
-        let DeviceBuilder {
-            app,
-            name,
-            redistributions,
-            x,
-            y,
-        } = self;
+    /// Set the key used by OSPF MD5 authentication
+    pub fn set_ospf_md5_key(&mut self, key: &str) {
+        self.ospf_md5_key = Some(key.to_string());
+    }
 
-        app.devices.insert(Device {
-            name,
-            redistributions,
-            x,
-            y,
-            next_iface: 0,
-        })
+    /// Set the passwords used by the devices which don't set their own
+    pub fn set_default_passwords(&mut self, passwords: Passwords) {
+        self.default_passwords = passwords;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Set the MOTD banner of the devices which don't set their own
+    pub fn set_default_banner(&mut self, banner: &str) {
+        self.default_banner = Some(banner.to_string());
+    }
 
-    #[test]
-    fn add_remove_ip() {
-        let mut app = App::new();
+    /// Encrypt the traffic between the networks behind two edge routers
+    pub fn add_vpn(&mut self, vpn: Vpn) {
+        let [a, b] = &vpn.peers;
+        assert_ne!(a.router, b.router);
+        self.vpns.push(vpn);
+    }
 
-        let r1 = app.add_device("R1").finish();
-        let r2 = app.add_device("R2").finish();
+    /// Bundle the link between `r1` and `r2` with other parallel links into
+    /// the LACP EtherChannel `group`, so that it has `members` interfaces per side
+    pub fn bundle(&mut self, r1: DefaultKey, r2: DefaultKey, group: u16, members: u8) {
+        assert_ne!(r1, r2);
+        assert!(members >= 2, "An EtherChannel needs at least two members");
 
-        app.link(r1, r2, "10.0.0.0/30", None);
-        assert_eq!(
-            app.get_directed_link(r1, r2).unwrap().close_ip,
-            "10.0.0.1/30".parse().unwrap(),
+        // Order `r1` and `r2`
+        let (r1, r2) = if r1 < r2 { (r1, r2) } else { (r2, r1) };
+
+        let key = self
+            .link_between(r1, r2)
+            .expect("Only existing links can be bundled");
+        let link = &mut self.links[key];
+        link.channel_group = Some(group);
+        for _ in 1..members {
+            link.r1_members.push(self.devices[r1].next_iface);
+            link.r2_members.push(self.devices[r2].next_iface);
+            self.devices[r1].next_iface += 1;
+            self.devices[r2].next_iface += 1;
+        }
+    }
+
+    /// Connect `hub` and `spoke` with a Frame Relay PVC carried by `cloud`,
+    /// identified by `dlcis` at the hub and spoke side respectively.
+    ///
+    /// The hub reaches all its spokes from the same serial interface,
+    /// with a point-to-point subinterface per PVC, while each spoke maps
+    /// the address of the hub on its own serial interface
+    pub fn frame_relay(
+        &mut self,
+        cloud: DefaultKey,
+        hub: DefaultKey,
+        spoke: DefaultKey,
+        ip: &str,
+        ospf_area: Option<u16>,
+        dlcis: [u16; 2],
+    ) -> &mut Link {
+        assert_ne!(hub, spoke);
+        assert_eq!(self.devices[cloud].kind, DeviceKind::FrameRelayCloud);
+
+        // Order `r1` and `r2`
+        let (r1, r2) = if hub < spoke {
+            (hub, spoke)
+        } else {
+            (spoke, hub)
+        };
+        assert!(
+            self.link_between(r1, r2).is_none(),
+            "The hub and the spoke are already connected"
         );
-        assert_eq!(
-            app.get_directed_link(r1, r2).unwrap().far_ip,
-            "10.0.0.2/30".parse().unwrap(),
+
+        // The hub keeps the interface connected to the cloud by its other PVCs
+        let mut hub_ports = None;
+        for link in self.links.values() {
+            let InterfaceType::FrameRelay(pvc) = link.interface_type else {
+                continue;
+            };
+            let key = link.ends;
+            if pvc.cloud != cloud {
+                continue;
+            }
+            let (pvc_hub_iface, pvc_spoke) = if key.0 == pvc.hub {
+                (link.r1_iface, key.1)
+            } else {
+                (link.r2_iface, key.0)
+            };
+            assert!(
+                pvc_spoke != spoke && pvc_spoke != hub && pvc.hub != spoke,
+                "Only the hub can have many PVCs on the same cloud"
+            );
+            if pvc.hub == hub {
+                hub_ports = Some((pvc_hub_iface, pvc.hub_port));
+            }
+        }
+        let (hub_iface, hub_port) = hub_ports.unwrap_or_else(|| {
+            let ports = (self.devices[hub].next_iface, self.devices[cloud].next_iface);
+            self.devices[hub].next_iface += 1;
+            self.devices[cloud].next_iface += 1;
+            ports
+        });
+        let (spoke_iface, spoke_port) = (
+            self.devices[spoke].next_iface,
+            self.devices[cloud].next_iface,
         );
-        assert_eq!(app.links.len(), 1);
+        self.devices[spoke].next_iface += 1;
+        self.devices[cloud].next_iface += 1;
 
-        app.unlink(r1, r2);
-        assert_eq!(app.links.len(), 0);
-        assert_eq!(app.get_directed_link(r1, r2), None);
+        let key = self.insert_link(Link {
+            ends: (r1, r2),
+            ..Default::default()
+        });
+        let link = &mut self.links[key];
+        let (hub_ip, spoke_ip) = link_addresses(ip);
+        if hub < spoke {
+            (link.r1, link.r2) = (hub_ip, spoke_ip);
+            (link.r1_iface, link.r2_iface) = (hub_iface, spoke_iface);
+        } else {
+            (link.r1, link.r2) = (spoke_ip, hub_ip);
+            (link.r1_iface, link.r2_iface) = (spoke_iface, hub_iface);
+        }
+        link.ospf_area = ospf_area;
+        link.interface_type = InterfaceType::FrameRelay(FrameRelayPvc {
+            cloud,
+            hub,
+            hub_dlci: dlcis[0],
+            spoke_dlci: dlcis[1],
+            hub_port,
+            spoke_port,
+        });
+        link
     }
 
-    #[test]
-    fn modify_link() {
-        let mut app = App::new();
+    /// Give the link between `r1` and `r2` an IPv6 subnet besides its IPv4 one,
+    /// so that both its sides run the two address families
+    pub fn dual_stack(&mut self, r1: DefaultKey, r2: DefaultKey, ipv6: &str) {
+        assert_ne!(r1, r2);
 
-        let r1 = app.add_device("R1").finish();
-        let r2 = app.add_device("R2").finish();
+        // Order `r1` and `r2`
+        let (r1, r2) = if r1 < r2 { (r1, r2) } else { (r2, r1) };
 
-        app.link(r1, r2, "10.0.0.0/30", None);
-        assert_eq!(
-            app.get_directed_link(r1, r2).unwrap().close_ip,
-            "10.0.0.1/30".parse().unwrap(),
-        );
-        assert_eq!(
-            app.get_directed_link(r1, r2).unwrap().far_ip,
-            "10.0.0.2/30".parse().unwrap(),
+        let key = self
+            .link_between(r1, r2)
+            .expect("Only existing links can be dual-stack");
+        let link = &mut self.links[key];
+        let (r1, r2) = link_addresses(ipv6);
+        assert!(
+            link.r1.addr().is_ipv4() && r1.addr().is_ipv6(),
+            "A dual-stack link needs an IPv4 and an IPv6 subnet"
         );
+        link.ipv6 = Some((r1, r2));
+    }
 
-        app.link(r2, r1, "10.0.0.4/30", None);
-        assert_eq!(
-            app.get_directed_link(r1, r2).unwrap().close_ip,
-            "10.0.0.5/30".parse().unwrap(),
+    /// Add to the link between `r1` and `r2` an 802.1Q subinterface per side,
+    /// which carries the frames of `vlan` and has an address in `ip`
+    pub fn subinterface(&mut self, r1: DefaultKey, r2: DefaultKey, vlan: u16, ip: &str) {
+        assert_ne!(r1, r2);
+
+        // Order `r1` and `r2`
+        let (r1, r2) = if r1 < r2 { (r1, r2) } else { (r2, r1) };
+
+        let key = self
+            .link_between(r1, r2)
+            .expect("Subinterfaces can only be added to existing links");
+        let link = &mut self.links[key];
+        assert!(
+            link.sublinks.iter().all(|sublink| sublink.vlan != vlan),
+            "VLAN {vlan} already has a subinterface"
         );
-        assert_eq!(
-            app.get_directed_link(r1, r2).unwrap().far_ip,
+        let (r1, r2) = link_addresses(ip);
+        link.sublinks.push(SubLink { vlan, r1, r2 });
+    }
+
+    /// Route between `vlans`, each given with its subnet, through `gateway`
+    ///
+    /// A router gets a subinterface per VLAN on its link towards the switches,
+    /// while a multilayer switch gets an SVI. The links between the switches
+    /// become trunks, and the hosts are put in the VLAN of their subnet
+    pub fn setup_intervlan_routing(&mut self, gateway: DefaultKey, vlans: &[(u16, &str)]) {
+        let vlans: Vec<(u16, IpNet)> = vlans
+            .iter()
+            .map(|&(vlan, subnet)| {
+                let subnet = IpNet::from_str(subnet)
+                    .unwrap_or_else(|_| panic!("Failed to parse ip: {subnet}"));
+                (vlan, subnet)
+            })
+            .collect();
+        let is_switch = |device: &Device| {
+            matches!(
+                device.kind,
+                DeviceKind::Switch | DeviceKind::MultilayerSwitch
+            )
+        };
+
+        // The gateway
+        if self.devices[gateway].kind == DeviceKind::MultilayerSwitch {
+            for &(vlan, subnet) in &vlans {
+                let ip = to_ipnet(subnet.hosts().next().unwrap(), subnet.prefix_len());
+                self.devices[gateway].svis.push(Svi { vlan, ip });
+            }
+        } else {
+            let switch = self
+                .links_of(gateway)
+                .iter()
+                .map(|link| link.far_key)
+                .find(|&far| is_switch(&self.devices[far]))
+                .expect("The gateway must be connected to a switch");
+            for &(vlan, subnet) in &vlans {
+                self.subinterface(gateway, switch, vlan, &subnet.to_string());
+            }
+            let key = self.link_between(gateway, switch).unwrap();
+            self.links[key].trunk.get_or_insert_with(Trunk::default);
+        }
+
+        // The switches reachable from the gateway through other switches
+        let mut switches = vec![];
+        let mut stack = vec![gateway];
+        while let Some(key) = stack.pop() {
+            for link in self.links_of(key) {
+                if is_switch(&self.devices[link.far_key]) && !switches.contains(&link.far_key) {
+                    switches.push(link.far_key);
+                    stack.push(link.far_key);
+                }
+            }
+        }
+
+        for &switch in &switches {
+            let device = &mut self.devices[switch];
+            for &(vlan, _) in &vlans {
+                if device.vlans.iter().all(|other| other.id != vlan) {
+                    device.vlans.push(Vlan {
+                        id: vlan,
+                        name: None,
+                    });
+                }
+            }
+        }
+
+        for link in self.links.values_mut() {
+            let (r1, r2) = link.ends;
+            let (host_ip, switch) = match (self.devices[r1].kind, self.devices[r2].kind) {
+                (kind, _) if kind.is_host() => (link.r1, r2),
+                (_, kind) if kind.is_host() => (link.r2, r1),
+                _ if switches.contains(&r1) && switches.contains(&r2) => {
+                    link.trunk.get_or_insert_with(Trunk::default);
+                    continue;
+                }
+                _ => continue,
+            };
+            if switches.contains(&switch) {
+                link.access_vlan = vlans
+                    .iter()
+                    .find(|(_, subnet)| subnet.contains(&host_ip.addr()))
+                    .map(|&(vlan, _)| vlan);
+            }
+        }
+    }
+
+    /// Attach `members` to a LAN segment through `switch`, giving each an
+    /// address of `subnet`, in order
+    pub fn segment(
+        &mut self,
+        switch: DefaultKey,
+        members: &[DefaultKey],
+        subnet: &str,
+        ospf_area: Option<u16>,
+    ) {
+        assert!(
+            matches!(
+                self.devices[switch].kind,
+                DeviceKind::Switch | DeviceKind::MultilayerSwitch
+            ),
+            "The devices of a segment must be attached to a switch"
+        );
+        let subnet =
+            IpNet::from_str(subnet).unwrap_or_else(|_| panic!("Failed to parse ip: {subnet}"));
+        let subnet = subnet.trunc();
+        self.reserve(subnet);
+
+        let mut hosts = subnet.hosts();
+        // The first host of an IPv6 subnet is the subnet-router anycast address
+        if subnet.addr().is_ipv6() {
+            hosts.next();
+        }
+        for &member in members {
+            let ip = hosts
+                .next()
+                .unwrap_or_else(|| panic!("The members of the segment don't fit in {subnet}"));
+            let ip = to_ipnet(ip, subnet.prefix_len());
+            let (key, addresses) = if member < switch {
+                (self.connect(member, switch), (ip, subnet))
+            } else {
+                (self.connect(switch, member), (subnet, ip))
+            };
+            let link = &mut self.links[key];
+            link.ospf_area = ospf_area;
+            (link.r1, link.r2) = addresses;
+        }
+        self.segments.push(Segment {
+            switch,
+            subnet,
+            members: members.to_vec(),
+        });
+    }
+
+    /// Add `n` routers, named from `{prefix}1` to `{prefix}{n}`, for a template
+    fn template_routers(&mut self, prefix: &str, n: usize) -> Vec<DefaultKey> {
+        (1..=n)
+            .map(|i| self.add_device(&format!("{prefix}{i}")).finish())
+            .collect()
+    }
+
+    /// Add `n` routers linked in a ring, each to the next one and the
+    /// last one to the first, with subnets taken from the address pool
+    pub fn template_ring(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        ospf_area: Option<u16>,
+    ) -> Vec<DefaultKey> {
+        assert!(n >= 3, "A ring needs at least 3 routers");
+        let routers = self.template_routers(prefix, n);
+        for i in 0..n {
+            let (r1, r2) = (routers[i], routers[(i + 1) % n]);
+            let subnet = self.allocate_subnet(r1, r2);
+            self.link(r1, r2, &subnet.to_string(), ospf_area);
+        }
+        routers
+    }
+
+    /// Add `n` routers, with the first one linked to each of the others,
+    /// with subnets taken from the address pool
+    pub fn template_star(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        ospf_area: Option<u16>,
+    ) -> Vec<DefaultKey> {
+        assert!(n >= 2, "A star needs at least 2 routers");
+        let routers = self.template_routers(prefix, n);
+        for &spoke in &routers[1..] {
+            let subnet = self.allocate_subnet(routers[0], spoke);
+            self.link(routers[0], spoke, &subnet.to_string(), ospf_area);
+        }
+        routers
+    }
+
+    /// Add `n` routers, each linked to all the others,
+    /// with subnets taken from the address pool
+    pub fn template_full_mesh(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        ospf_area: Option<u16>,
+    ) -> Vec<DefaultKey> {
+        assert!(n >= 2, "A mesh needs at least 2 routers");
+        let routers = self.template_routers(prefix, n);
+        for i in 0..n {
+            for j in i + 1..n {
+                let (r1, r2) = (routers[i], routers[j]);
+                let subnet = self.allocate_subnet(r1, r2);
+                self.link(r1, r2, &subnet.to_string(), ospf_area);
+            }
+        }
+        routers
+    }
+
+    /// Add `n` routers and a Frame Relay cloud named `{prefix}Cloud`, with
+    /// a PVC from the first router, the hub, to each of the others,
+    /// with subnets taken from the address pool
+    ///
+    /// The DLCI of each PVC is 100 plus the number of the router it reaches,
+    /// so `{prefix}1` reaches `{prefix}3` through DLCI 103. The cloud is
+    /// returned after the routers
+    pub fn template_hub_and_spoke(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        ospf_area: Option<u16>,
+    ) -> Vec<DefaultKey> {
+        assert!(
+            (2..=900).contains(&n),
+            "A hub and spoke topology needs from 2 to 900 routers"
+        );
+        let routers = self.template_routers(prefix, n);
+        let cloud = self
+            .add_device(&format!("{prefix}Cloud"))
+            .kind(DeviceKind::FrameRelayCloud)
+            .finish();
+        for (i, &spoke) in routers.iter().enumerate().skip(1) {
+            let subnet = self.allocate_subnet(routers[0], spoke);
+            let dlcis = [101 + i as u16, 101];
+            self.frame_relay(
+                cloud,
+                routers[0],
+                spoke,
+                &subnet.to_string(),
+                ospf_area,
+                dlcis,
+            );
+        }
+        let mut devices = routers;
+        devices.push(cloud);
+        devices
+    }
+
+    /// Disconnect the two devices if they are connected, removing
+    /// all the links between them
+    pub fn unlink(&mut self, r1: DefaultKey, r2: DefaultKey) {
+        assert_ne!(r1, r2);
+
+        // Order `r1` and `r2`
+        let key = if r1 < r2 { (r1, r2) } else { (r2, r1) };
+
+        for link in self.adjacency.remove(&key).unwrap_or_default() {
+            self.links.remove(link);
+        }
+    }
+
+    /// Rename a device, updating the settings of the other devices
+    /// which refer to it by name
+    pub fn rename_device(&mut self, key: DefaultKey, new_name: &str) -> Result<(), String> {
+        if self
+            .devices
+            .iter()
+            .any(|(other, device)| other != key && device.name == new_name)
+        {
+            return Err(format!("There already is a device named `{new_name}`"));
+        }
+        let old_name = std::mem::replace(&mut self.devices[key].name, new_name.to_string());
+
+        let rename = |name: &mut String| {
+            if *name == old_name {
+                *name = new_name.to_string();
+            }
+        };
+        for device in self.devices.values_mut() {
+            if let Some(logging) = &mut device.logging {
+                rename(&mut logging.server);
+            }
+            if let Some(snmp) = &mut device.snmp {
+                for host in &mut snmp.trap_hosts {
+                    rename(&mut host.server);
+                }
+            }
+        }
+        for link in self.links.values_mut() {
+            for options in [&mut link.r1_options, &mut link.r2_options] {
+                if let Some(DhcpRelay::Device(server)) = &mut options.dhcp_relay {
+                    rename(server);
+                }
+                if let Some(track) = &mut options.fhrp_track {
+                    rename(&mut track.towards);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a device, with its links and everything referring to it
+    ///
+    /// The interfaces of the removed links aren't reused by the neighbours,
+    /// just like after `unlink`
+    pub fn remove_device(&mut self, key: DefaultKey) -> Device {
+        let device = self.devices.remove(key).expect("The device doesn't exist");
+
+        // The PVCs carried by a removed cloud go away with it
+        self.links.retain(|_, link| {
+            let carried =
+                matches!(link.interface_type, InterfaceType::FrameRelay(pvc) if pvc.cloud == key);
+            link.ends.0 != key && link.ends.1 != key && !carried
+        });
+        let links = &self.links;
+        self.adjacency.retain(|_, keys| {
+            keys.retain(|&link| links.contains_key(link));
+            !keys.is_empty()
+        });
+        self.rip_enabled.retain(|&other| other != key);
+        self.segments.retain(|segment| segment.switch != key);
+        for segment in &mut self.segments {
+            segment.members.retain(|&member| member != key);
+        }
+        for site in &mut self.sites {
+            site.members.retain(|&member| member != key);
+        }
+        self.vpns.retain(|vpn| {
+            vpn.peers
+                .iter()
+                .all(|peer| peer.router != key && peer.outside != key)
+        });
+
+        for other in self.devices.values_mut() {
+            other.bgp_rr_clients.retain(|&client| client != key);
+            other.bgp_policies.retain(|policy| policy.neighbor != key);
+            other.summaries.retain(|summary| match summary.protocol {
+                SummaryProtocol::Eigrp { towards } | SummaryProtocol::Rip { towards } => {
+                    towards != key
+                }
+                SummaryProtocol::Ospf { .. } => true,
+            });
+            if other.default_route == Some(DefaultRoute::ExitInterface(key)) {
+                other.default_route = None;
+            }
+            if matches!(&other.dhcp_snooping, Some(snooping) if snooping.server == key) {
+                other.dhcp_snooping = None;
+            }
+
+            // The references by name updated by `rename_device`
+            if matches!(&other.logging, Some(logging) if logging.server == device.name) {
+                other.logging = None;
+            }
+            if let Some(snmp) = &mut other.snmp {
+                snmp.trap_hosts.retain(|host| host.server != device.name);
+            }
+        }
+        for link in self.links.values_mut() {
+            for options in [&mut link.r1_options, &mut link.r2_options] {
+                if matches!(&options.dhcp_relay, Some(DhcpRelay::Device(server)) if *server == device.name)
+                {
+                    options.dhcp_relay = None;
+                }
+                if matches!(&options.fhrp_track, Some(track) if track.towards == device.name) {
+                    options.fhrp_track = None;
+                }
+            }
+        }
+        device
+    }
+
+    /// The devices linked to `key`
+    fn neighbors(&self, key: DefaultKey) -> impl Iterator<Item = DefaultKey> + '_ {
+        self.adjacency.keys().filter_map(move |&(r1, r2)| {
+            if r1 == key {
+                Some(r2)
+            } else if r2 == key {
+                Some(r1)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Find the shortest sequence of links from `from` to `to`, returning
+    /// every device along the way, both ends included
+    pub fn path_between(&self, from: DefaultKey, to: DefaultKey) -> Option<Vec<DefaultKey>> {
+        let mut previous = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(key) = queue.pop_front() {
+            if key == to {
+                let mut path = vec![to];
+                while *path.last().unwrap() != from {
+                    path.push(previous[path.last().unwrap()]);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for neighbor in self.neighbors(key) {
+                if let Entry::Vacant(entry) = previous.entry(neighbor) {
+                    entry.insert(key);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    /// Split the devices into groups which can reach each other
+    ///
+    /// Frame Relay clouds are left out, since the PVCs crossing them
+    /// already link the routers directly
+    pub fn partitions(&self) -> Vec<Vec<DefaultKey>> {
+        let mut partitions = vec![];
+        let mut visited = BTreeSet::new();
+        for (key, device) in &self.devices {
+            if device.kind == DeviceKind::FrameRelayCloud || visited.contains(&key) {
+                continue;
+            }
+            let mut partition = vec![key];
+            visited.insert(key);
+            let mut i = 0;
+            while i < partition.len() {
+                for neighbor in self.neighbors(partition[i]) {
+                    if visited.insert(neighbor) {
+                        partition.push(neighbor);
+                    }
+                }
+                i += 1;
+            }
+            partition.sort();
+            partitions.push(partition);
+        }
+        partitions
+    }
+
+    /// Whether every device can reach every other one
+    pub fn is_connected(&self) -> bool {
+        self.partitions().len() <= 1
+    }
+
+    /// Give a position to the devices in `devices`, laying them out
+    /// by their distance from each other
+    ///
+    /// The other devices are left where they are, so that the positions
+    /// given by hand are kept
+    pub fn auto_layout(&mut self, devices: &[DefaultKey]) {
+        let positions = layout::layered(self);
+        for &key in devices {
+            let (x, y) = positions[&key];
+            let device = &mut self.devices[key];
+            device.x = x;
+            device.y = y;
+        }
+    }
+
+    /// Check the whole topology, returning every issue found
+    ///
+    /// Besides running the other validations, this looks for devices which
+    /// can't take part in the network, and for routing protocols which
+    /// are enabled but can't work
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+
+        for (key, device) in &self.devices {
+            // Clouds only switch the PVCs between the routers
+            if device.kind == DeviceKind::FrameRelayCloud {
+                continue;
+            }
+            let links = self.links_of(key);
+            if links.is_empty() && device.loopbacks.is_empty() && device.svis.is_empty() {
+                issues.push(ValidationIssue::warning(
+                    IssueCode::NoInterfaces,
+                    [device],
+                    format!("Device `{}` has no interfaces", device.name),
+                ));
+            } else if links.is_empty() {
+                issues.push(ValidationIssue::warning(
+                    IssueCode::IsolatedDevice,
+                    [device],
+                    format!("Device `{}` is not linked to any other device", device.name),
+                ));
+            }
+
+            if let Some(logging) = &device.logging {
+                if let Some(reason) = self.unreachable_server(&logging.server) {
+                    issues.push(ValidationIssue::error(
+                        IssueCode::UnreachableServer,
+                        [device],
+                        format!(
+                            "Device `{}` logs to `{}`, but {reason}",
+                            device.name, logging.server
+                        ),
+                    ));
+                }
+            }
+            if let Some(ssh) = &device.ssh {
+                if !(1..=16).contains(&ssh.vty_lines) {
+                    issues.push(ValidationIssue::error(
+                        IssueCode::VtyLines,
+                        [device],
+                        format!(
+                            "Device `{}` accepts SSH on {} VTY lines, but it must be between 1 and 16",
+                            device.name, ssh.vty_lines
+                        ),
+                    ));
+                }
+            }
+            for host in device.snmp.iter().flat_map(|snmp| &snmp.trap_hosts) {
+                if let Some(reason) = self.unreachable_server(&host.server) {
+                    issues.push(ValidationIssue::error(
+                        IssueCode::UnreachableServer,
+                        [device],
+                        format!(
+                            "Device `{}` sends SNMP traps to `{}`, but {reason}",
+                            device.name, host.server
+                        ),
+                    ));
+                }
+            }
+
+            let rip_neighbors = links.iter().any(|link| {
+                let mut far_routers = link.far_routers.iter();
+                far_routers.any(|far| self.rip_enabled.contains(far))
+            });
+            if self.rip_enabled.contains(&key) && !rip_neighbors {
+                issues.push(ValidationIssue::warning(
+                    IssueCode::RipWithoutNeighbors,
+                    [device],
+                    format!(
+                        "Device `{}` runs RIP, but none of its neighbours do",
+                        device.name
+                    ),
+                ));
+            }
+        }
+
+        for link in self.links.values() {
+            let sides = [
+                (link.ends.0, link.ends.1, &link.r1_options),
+                (link.ends.1, link.ends.0, &link.r2_options),
+            ];
+            for (key, far, options) in sides {
+                let device = &self.devices[key];
+                if let Some(loopback) = options.unnumbered {
+                    if device.loopbacks.len() <= loopback as usize {
+                        issues.push(ValidationIssue::error(
+                            IssueCode::UnnumberedWithoutLoopback,
+                            [device],
+                            format!(
+                                "The interface of `{}` towards `{}` is unnumbered, but there is no Loopback{loopback} to borrow the address of",
+                                device.name, self.devices[far].name
+                            ),
+                        ));
+                    }
+                }
+
+                let Some(DhcpRelay::Device(server)) = &options.dhcp_relay else {
+                    continue;
+                };
+                if let Some(reason) = self.unreachable_server(server) {
+                    issues.push(ValidationIssue::error(
+                        IssueCode::UnreachableServer,
+                        [device],
+                        format!(
+                            "Device `{}` relays DHCP requests to `{server}`, but {reason}",
+                            device.name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Isolated devices were already reported above
+        let partitions: Vec<_> = self
+            .partitions()
+            .into_iter()
+            .filter(|partition| partition.len() > 1)
+            .collect();
+        if partitions.len() > 1 {
+            let names: Vec<_> = partitions
+                .iter()
+                .map(|partition| {
+                    let names: Vec<_> = partition
+                        .iter()
+                        .map(|&key| self.devices[key].name.as_str())
+                        .collect();
+                    names.join(", ")
+                })
+                .collect();
+            issues.push(ValidationIssue::warning(
+                IssueCode::Partitioned,
+                [],
+                format!(
+                    "The network is split into parts which can't reach each other: {}",
+                    names.join(" | ")
+                ),
+            ));
+        }
+
+        // The areas must be attached to the backbone to exchange routes
+        let areas: BTreeSet<u16> = self
+            .links
+            .values()
+            .filter_map(|link| link.ospf_area)
+            .collect();
+        if !areas.is_empty() && !areas.contains(&0) {
+            for area in areas {
+                let devices = self
+                    .links
+                    .values()
+                    .filter(|link| link.ospf_area == Some(area))
+                    .flat_map(|link| [&self.devices[link.ends.0], &self.devices[link.ends.1]]);
+                issues.push(ValidationIssue::warning(
+                    IssueCode::MissingBackboneArea,
+                    devices,
+                    format!("OSPF area {area} is used, but no link is in the backbone area 0"),
+                ));
+            }
+        }
+
+        let validations = [
+            (IssueCode::RouteReflector, self.validate_route_reflectors()),
+            (IssueCode::FloatingRoute, self.validate_floating_routes()),
+            (IssueCode::AddressConflict, self.validate_addresses()),
+        ];
+        for (code, result) in validations {
+            if let Err(message) = result {
+                issues.push(ValidationIssue::error(code, [], message));
+            }
+        }
+        if let Err(message) = self.validate_vtp_passwords() {
+            issues.push(ValidationIssue::warning(
+                IssueCode::VtpPasswordMismatch,
+                [],
+                message,
+            ));
+        }
+
+        issues
+    }
+
+    /// Check that, in every AS using route reflectors, each device is
+    /// either a route reflector or a client of one
+    pub fn validate_route_reflectors(&self) -> Result<(), String> {
+        let is_rr = |dev: &Device| !dev.bgp_rr_clients.is_empty();
+
+        for (key, device) in &self.devices {
+            let Some(bgp_as) = device.bgp_as else {
+                continue;
+            };
+            let same_as = || {
+                self.devices
+                    .values()
+                    .filter(|dev| dev.bgp_as == Some(bgp_as))
+            };
+
+            if same_as().any(is_rr)
+                && !is_rr(device)
+                && !same_as().any(|rr| rr.bgp_rr_clients.contains(&key))
+            {
+                return Err(format!(
+                    "Device `{}` in AS {bgp_as} is not a client of any route reflector",
+                    device.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that all the switches of a VTP domain use the same password,
+    /// otherwise they ignore each other's advertisements
+    pub fn validate_vtp_passwords(&self) -> Result<(), String> {
+        let mut passwords: HashMap<&str, (&str, Option<&str>)> = HashMap::new();
+        for device in self.devices.values() {
+            let Some(vtp) = &device.vtp else {
+                continue;
+            };
+            let password = vtp.password.as_deref();
+            let (first, expected) = *passwords
+                .entry(&vtp.domain)
+                .or_insert((&device.name, password));
+            if expected != password {
+                return Err(format!(
+                    "Switches `{first}` and `{}` of VTP domain `{}` have different passwords",
+                    device.name, vtp.domain
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that every floating static route backs up another route to
+    /// the same prefix: either a static route with a lower distance, or a
+    /// dynamic protocol run by the device which advertises a link inside it
+    pub fn validate_floating_routes(&self) -> Result<(), String> {
+        for (key, device) in &self.devices {
+            let links = self.links_of(key);
+            let runs_dynamic_protocol = device.bgp_as.is_some()
+                || device.isis_net.is_some()
+                || links.iter().any(|link| {
+                    link.ospf_area.is_some()
+                        || link.eigrp
+                        || link
+                            .far_routers
+                            .iter()
+                            .any(|far| self.rip_enabled.contains(far))
+                });
+            let advertised = |prefix: &IpNet| {
+                runs_dynamic_protocol && self.links.values().any(|link| prefix.contains(&link.r1))
+            };
+
+            for route in &device.static_routes {
+                let Some(distance) = route.distance else {
+                    continue;
+                };
+                let has_primary = device.static_routes.iter().any(|primary| {
+                    primary.prefix == route.prefix && primary.distance.unwrap_or(1) < distance
+                });
+
+                if !has_primary && !advertised(&route.prefix) {
+                    return Err(format!(
+                        "Floating route to {} on device `{}` doesn't back up any route",
+                        route.prefix, device.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that no address is configured twice, and that the subnets of
+    /// the interfaces don't overlap unless they are the same LAN
+    ///
+    /// Only the addresses configured on layer 3 interfaces are considered,
+    /// and the ones in different VRFs never conflict
+    pub fn validate_addresses(&self) -> Result<(), String> {
+        // The device, interface, VRF and address of every layer 3 interface
+        let mut interfaces = vec![];
+        for (key, device) in &self.devices {
+            let ctx = DeviceContext {
+                app: self,
+                key,
+                device,
+                links: self.links_of(key),
+            };
+            for link in &ctx.links {
+                if switching::is_switchport(&ctx, link) || link.close_options.unnumbered.is_some() {
+                    continue;
+                }
+                let iface = link.iface_name();
+                let vrf = link.close_options.vrf.clone();
+                for ip in std::iter::once(link.close_ip).chain(link.close_ipv6) {
+                    // The interface identifiers of EUI-64 addresses aren't known
+                    if !(link.eui64 && ip.addr().is_ipv6()) {
+                        interfaces.push((&device.name, iface.clone(), vrf.clone(), ip));
+                    }
+                }
+                for &(vlan, ip) in &link.close_subinterfaces {
+                    let subinterface = format!("{iface}.{vlan}");
+                    interfaces.push((&device.name, subinterface, vrf.clone(), ip));
+                }
+            }
+            for (i, &ip) in device.loopbacks.iter().enumerate() {
+                interfaces.push((&device.name, format!("Loopback{i}"), None, ip));
+            }
+            for svi in &device.svis {
+                interfaces.push((&device.name, format!("Vlan{}", svi.vlan), None, svi.ip));
+            }
+        }
+
+        for (i, (device, iface, vrf, ip)) in interfaces.iter().enumerate() {
+            for (other_device, other_iface, other_vrf, other_ip) in &interfaces[i + 1..] {
+                if vrf != other_vrf {
+                    continue;
+                }
+                let both = format!("`{device}` ({iface}) and `{other_device}` ({other_iface})");
+                if ip.addr() == other_ip.addr() {
+                    return Err(format!("{both} have the same address {}", ip.addr()));
+                }
+                // Different devices can share a LAN, but not a device with itself
+                let overlaps = ip.contains(&other_ip.network()) || other_ip.contains(&ip.network());
+                if overlaps && (device == other_device || ip.trunc() != other_ip.trunc()) {
+                    return Err(format!(
+                        "{both} have the overlapping subnets {} and {}",
+                        ip.trunc(),
+                        other_ip.trunc()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Iterate over the links, with the keys of the two devices they connect
+    pub fn links(&self) -> impl Iterator<Item = (DefaultKey, DefaultKey, LinkView<'_>)> {
+        self.links
+            .values()
+            .map(|link| (link.ends.0, link.ends.1, LinkView { link }))
+    }
+
+    /// Retrieve the last link created between two devices, to modify its options
+    pub fn link_mut(&mut self, r1: DefaultKey, r2: DefaultKey) -> Option<&mut Link> {
+        let key = self.link_between(r1, r2)?;
+        self.links.get_mut(key)
+    }
+
+    /// The keys of all the links between two devices, from the oldest
+    pub fn links_between(&self, r1: DefaultKey, r2: DefaultKey) -> &[LinkKey] {
+        let ends = if r1 < r2 { (r1, r2) } else { (r2, r1) };
+        self.adjacency.get(&ends).map_or(&[], Vec::as_slice)
+    }
+
+    /// Retrieve a link by key
+    pub fn link_by_key(&self, key: LinkKey) -> Option<LinkView<'_>> {
+        let link = self.links.get(key)?;
+        Some(LinkView { link })
+    }
+
+    /// Retrieve a link by key, to modify its options
+    pub fn link_by_key_mut(&mut self, key: LinkKey) -> Option<&mut Link> {
+        self.links.get_mut(key)
+    }
+
+    /// All the links of a device, ordered by interface number
+    pub fn links_of(&self, close_key: DefaultKey) -> Vec<DirectedLink> {
+        let mut links: Vec<DirectedLink> = self
+            .links
+            .iter()
+            .filter(|(_, link)| link.ends.0 == close_key || link.ends.1 == close_key)
+            .map(|(key, _)| self.directed_link(key, close_key))
+            .collect();
+
+        links.sort_by_key(|link| link.close_iface);
+        links
+    }
+
+    /// Generate the configuration of every device, to print to the user
+    pub fn to_commands(&self) -> BTreeMap<String, Config> {
+        let mut map = BTreeMap::new();
+
+        for (close_key, device) in &self.devices {
+            if device.kind.is_host() {
+                let ctx = DeviceContext {
+                    app: self,
+                    key: close_key,
+                    device,
+                    links: self.links_of(close_key),
+                };
+                let settings = host::write_settings(&ctx);
+                map.insert(device.name.clone(), Config::HostSettings(settings));
+                continue;
+            }
+            if device.kind == DeviceKind::FrameRelayCloud {
+                let settings = frame_relay::write_cloud(self, close_key);
+                map.insert(device.name.clone(), Config::HostSettings(settings));
+                continue;
+            }
+            let ctx = DeviceContext {
+                app: self,
+                key: close_key,
+                device,
+                links: self.links_of(close_key),
+            };
+
+            // Header
+            let mut res = String::from("enable\nconfigure terminal\n\n");
+            writeln!(res, "hostname {}", hostname(&device.name)).unwrap();
+            if device.kind == DeviceKind::MultilayerSwitch {
+                res.push_str("ip routing\n");
+            }
+            if device.kind.routes() && ctx.has_ipv6() {
+                res.push_str("ipv6 unicast-routing\n");
+            }
+            res.push('\n');
+
+            management::write_passwords(&ctx, &mut res);
+            management::write_banner(&ctx, &mut res);
+
+            // Name resolution
+            let site = self.site_of(close_key);
+            let dns = &device.dns;
+            let domain_name = (dns.domain_name.as_ref())
+                .or_else(|| site.and_then(|site| site.domain_name.as_ref()));
+            if let Some(domain_name) = domain_name {
+                writeln!(res, "ip domain-name {domain_name}").unwrap();
+            }
+            for name_server in &dns.name_servers {
+                writeln!(res, "ip name-server {name_server}").unwrap();
+            }
+            match dns.domain_lookup {
+                Some(true) => res.push_str("ip domain-lookup\n"),
+                Some(false) => res.push_str("no ip domain-lookup\n"),
+                None => {}
+            }
+            if *dns != DnsSettings::default() || domain_name.is_some() {
+                res.push('\n');
+            }
+
+            let ntp_server = device
+                .ntp_server
+                .or_else(|| site.and_then(|site| site.ntp_server));
+            if let Some(ntp_server) = ntp_server {
+                writeln!(res, "ntp server {ntp_server}\n").unwrap();
+            }
+
+            management::write_logging(&ctx, &mut res);
+            management::write_snmp(&ctx, &mut res);
+            management::write_users(&ctx, &mut res);
+            management::write_ssh(&ctx, &mut res);
+            management::write_discovery(&ctx, &mut res);
+
+            // Key chains
+            for chain in &device.key_chains {
+                writeln!(res, "key chain {}", chain.name).unwrap();
+                for (id, key) in &chain.keys {
+                    writeln!(res, "   key {id}\n      key-string {key}\n   exit").unwrap();
+                }
+                res.push_str("exit\n\n");
+            }
+
+            // Route-maps
+            for route_map in &device.route_maps {
+                for entry in &route_map.entries {
+                    let action = entry.action.keyword();
+                    writeln!(res, "route-map {} {action} {}", route_map.name, entry.seq).unwrap();
+                    for clause in &entry.matches {
+                        writeln!(res, "   match {clause}").unwrap();
+                    }
+                    for clause in &entry.sets {
+                        writeln!(res, "   set {clause}").unwrap();
+                    }
+                    res.push_str("exit\n");
+                }
+                res.push('\n');
+            }
+
+            // Prefix-lists
+            for list in &device.prefix_lists {
+                for entry in &list.entries {
+                    let action = entry.action.keyword();
+                    write!(
+                        res,
+                        "ip prefix-list {} seq {} {action} {}",
+                        list.name,
+                        entry.seq,
+                        entry.prefix.trunc(),
+                    )
+                    .unwrap();
+                    if let Some(ge) = entry.ge {
+                        write!(res, " ge {ge}").unwrap();
+                    }
+                    if let Some(le) = entry.le {
+                        write!(res, " le {le}").unwrap();
+                    }
+                    res.push('\n');
+                }
+            }
+            if !device.prefix_lists.is_empty() {
+                res.push('\n');
+            }
+
+            acl::write_acls(&ctx, &mut res);
+            vpn::write_crypto(&ctx, &mut res);
+            qos::write_policies(&ctx, &mut res);
+            autoconfig::write_pools(&ctx, &mut res);
+
+            // VRFs
+            for vrf in &device.vrfs {
+                writeln!(res, "vrf definition {}", vrf.name).unwrap();
+                if let Some(rd) = &vrf.rd {
+                    writeln!(res, "   rd {rd}").unwrap();
+                }
+                res.push_str("   address-family ipv4\n   exit-address-family\nexit\n\n");
+            }
+
+            // VTP
+            if let Some(vtp) = &device.vtp {
+                let mode = match vtp.mode {
+                    VtpMode::Server => "server",
+                    VtpMode::Client => "client",
+                    VtpMode::Transparent => "transparent",
+                };
+                writeln!(res, "vtp domain {}\nvtp mode {mode}", vtp.domain).unwrap();
+                if let Some(password) = &vtp.password {
+                    writeln!(res, "vtp password {password}").unwrap();
+                }
+                res.push('\n');
+            }
+
+            // VLANs
+            for vlan in &device.vlans {
+                writeln!(res, "vlan {}", vlan.id).unwrap();
+                if let Some(name) = &vlan.name {
+                    writeln!(res, "   name {name}").unwrap();
+                }
+                res.push_str("exit\n\n");
+            }
+
+            // Spanning tree
+            let stp = &device.spanning_tree;
+            match stp.mode {
+                Some(StpMode::Pvst) => res.push_str("spanning-tree mode pvst\n"),
+                Some(StpMode::RapidPvst) => res.push_str("spanning-tree mode rapid-pvst\n"),
+                None => {}
+            }
+            for (vlan, priority) in &stp.priorities {
+                writeln!(res, "spanning-tree vlan {vlan} priority {priority}").unwrap();
+            }
+            for vlan in &stp.root_primary_for {
+                writeln!(res, "spanning-tree vlan {vlan} root primary").unwrap();
+            }
+            if *stp != SpanningTree::default() {
+                res.push('\n');
+            }
+
+            // DHCP snooping
+            if let Some(snooping) = &device.dhcp_snooping {
+                let vlans: Vec<_> = snooping.vlans.iter().map(u16::to_string).collect();
+                writeln!(
+                    res,
+                    "ip dhcp snooping\nip dhcp snooping vlan {}\n",
+                    vlans.join(",")
+                )
+                .unwrap();
+            }
+
+            // Multicast
+            if let Some(multicast) = &device.multicast {
+                res.push_str("ip multicast-routing\n");
+                if let Some(rp) = multicast.rp_address {
+                    writeln!(res, "ip pim rp-address {rp}").unwrap();
+                }
+                res.push('\n');
+            }
+
+            fhrp::write_track_objects(&ctx, &mut res);
+
+            // Network interfaces
+            for link in &ctx.links {
+                if frame_relay::is_pvc(link) {
+                    continue;
+                }
+                if switching::is_switchport(&ctx, link) {
+                    switching::write_interface(&ctx, &mut res, link);
+                    continue;
+                }
+                assert!(
+                    link.channel_group.is_none(),
+                    "EtherChannels are only supported between switch ports"
+                );
+                writeln!(res, "interface {}", link.iface_name()).unwrap();
+                ctx.write_description(&mut res, link, link.far_iface);
+                if device.kind == DeviceKind::MultilayerSwitch {
+                    res.push_str("   no switchport\n");
+                }
+                // Assigning a VRF removes the addresses, so it must come first
+                if let Some(vrf) = &link.close_options.vrf {
+                    writeln!(res, "   vrf forwarding {vrf}").unwrap();
+                }
+                if let Some(loopback) = link.close_options.unnumbered {
+                    writeln!(res, "   ip unnumbered Loopback{loopback}").unwrap();
+                } else {
+                    write_link_address(&mut res, link, link.close_ip);
+                }
+                if let Some(ipv6) = link.close_ipv6 {
+                    write_link_address(&mut res, link, ipv6);
+                }
+                if let InterfaceType::Serial { dce, clock_rate } = link.interface_type {
+                    if dce == link.close_key {
+                        writeln!(res, "   clock rate {clock_rate}").unwrap();
+                    }
+                }
+                if let Some(ppp) = &link.ppp {
+                    assert!(
+                        matches!(link.interface_type, InterfaceType::Serial { .. }),
+                        "PPP is only supported on serial links"
+                    );
+                    res.push_str("   encapsulation ppp\n");
+                    match ppp.authentication {
+                        Some(PppAuthentication::Chap) => {
+                            res.push_str("   ppp authentication chap\n");
+                        }
+                        Some(PppAuthentication::Pap) => {
+                            res.push_str("   ppp authentication pap\n");
+                            writeln!(
+                                res,
+                                "   ppp pap sent-username {} password {}",
+                                hostname(&device.name),
+                                ppp.password,
+                            )
+                            .unwrap();
+                        }
+                        None => {}
+                    }
+                }
+                if let Some(bandwidth) = link.close_options.bandwidth.or(link.bandwidth) {
+                    writeln!(res, "   bandwidth {bandwidth}").unwrap();
+                }
+                if let Some(delay) = link.close_options.delay.or(link.delay) {
+                    writeln!(res, "   delay {delay}").unwrap();
+                }
+                if let Some(mtu) = link.mtu {
+                    writeln!(res, "   mtu {mtu}\n   ip mtu {mtu}").unwrap();
+                }
+                if let Some(bfd) = link.bfd {
+                    writeln!(
+                        res,
+                        "   bfd interval {} min_rx {} multiplier {}",
+                        bfd.interval, bfd.min_rx, bfd.multiplier,
+                    )
+                    .unwrap();
+                }
+                if link.pim && device.multicast.is_some() {
+                    res.push_str("   ip pim sparse-mode\n");
+                }
+                if let Some(route_map) = &link.close_options.policy_route_map {
+                    writeln!(res, "   ip policy route-map {route_map}").unwrap();
+                }
+                acl::write_interface(&mut res, link);
+                vpn::write_interface(&ctx, &mut res, link);
+                qos::write_interface(&mut res, link);
+                management::write_interface(&mut res, link);
+                autoconfig::write_interface(&mut res, link);
+                match &link.close_options.dhcp_relay {
+                    Some(DhcpRelay::Address(address)) => {
+                        writeln!(res, "   ip helper-address {address}").unwrap();
+                    }
+                    // A server without an address is reported by `validate`
+                    Some(DhcpRelay::Device(name)) => {
+                        if let Some(address) = self.device_address(name) {
+                            writeln!(res, "   ip helper-address {address}").unwrap();
+                        }
+                    }
+                    None => {}
+                }
+                fhrp::write_interface(&ctx, &mut res, link);
+                protocols::rip::write_interface(&ctx, &mut res, link);
+                protocols::ospf::write_interface(&ctx, &mut res, link);
+                protocols::eigrp::write_interface(&ctx, &mut res, link);
+                protocols::isis::write_interface(&ctx, &mut res, link);
+                write_shutdown(&mut res, link);
+                res.push_str("exit\n\n");
+
+                for (vlan, ip) in &link.close_subinterfaces {
+                    let iface = link.iface_name();
+                    writeln!(res, "interface {iface}.{vlan}").unwrap();
+                    writeln!(res, "   encapsulation dot1Q {vlan}").unwrap();
+                    write_address(&mut res, *ip);
+                    res.push_str("exit\n\n");
+                }
+            }
+            frame_relay::write_interfaces(&ctx, &mut res);
+
+            // Loopbacks
+            for (i, ip) in device.loopbacks.iter().enumerate() {
+                writeln!(res, "interface Loopback{i}").unwrap();
+                write_address(&mut res, *ip);
+                res.push_str("exit\n\n");
+            }
+
+            switching::write_svis(&ctx, &mut res);
+
+            if device.kind.routes() {
+                write_routing(&ctx, &mut res);
+            }
+
+            res.push_str("\nexit\ndisable\n");
+            map.insert(device.name.clone(), Config::Cli(res));
+        }
+
+        map
+    }
+}
+
+/// A network device
+#[derive(Default, Debug, PartialEq)]
+pub struct Device {
+    pub name: String,
+    pub kind: DeviceKind,
+    pub x: f32,
+    pub y: f32,
+    pub redistributions: Redistributions,
+    pub rip: RipSettings,
+    pub dns: DnsSettings,
+    /// Where the device sends its log messages, if anywhere
+    pub logging: Option<Logging>,
+    pub snmp: Option<Snmp>,
+    pub passwords: Passwords,
+    /// The message of the day, where `{name}` is replaced by the device name
+    pub banner: Option<String>,
+    pub users: Vec<LocalUser>,
+    /// The AAA method lists, if the device uses the AAA model
+    pub aaa: Option<Aaa>,
+    /// The SSH server settings, if the VTY lines are reached through SSH
+    pub ssh: Option<Ssh>,
+    /// Whether CDP runs on the device, if not left to the default of running
+    pub cdp: Option<bool>,
+    /// Whether LLDP runs on the device, if not left to the default of not running
+    pub lldp: Option<bool>,
+    pub key_chains: Vec<KeyChain>,
+    pub vrfs: Vec<Vrf>,
+    /// The addresses of the loopback interfaces, advertised by the routing
+    /// protocols the device runs
+    pub loopbacks: Vec<IpNet>,
+    pub vlans: Vec<Vlan>,
+    /// The VLAN interfaces of a switch
+    pub svis: Vec<Svi>,
+    /// The VTP settings, if the switch shares its VLANs through VTP
+    pub vtp: Option<Vtp>,
+    pub spanning_tree: SpanningTree,
+    pub dhcp_snooping: Option<DhcpSnooping>,
+    /// The multicast settings, if the device routes multicast traffic
+    pub multicast: Option<Multicast>,
+    pub route_maps: Vec<RouteMap>,
+    pub prefix_lists: Vec<PrefixList>,
+    pub acls: Vec<Acl>,
+    pub class_maps: Vec<ClassMap>,
+    pub policy_maps: Vec<PolicyMap>,
+    pub distribute_lists: Vec<DistributeList>,
+    /// The EIGRP autonomous system number, if the device runs EIGRP
+    pub eigrp_as: Option<u16>,
+    /// How many times worse than the best one an EIGRP route can be
+    /// while still being used for unequal-cost load balancing
+    pub eigrp_variance: Option<u8>,
+    pub eigrp_k_values: Option<EigrpKValues>,
+    /// The BGP autonomous system number, if the device runs BGP
+    pub bgp_as: Option<u32>,
+    /// The iBGP peers this device reflects routes to. If any device of an
+    /// AS has clients, iBGP sessions are no longer a full mesh
+    pub bgp_rr_clients: Vec<DefaultKey>,
+    /// Route-maps applied to the routes exchanged with BGP neighbors
+    pub bgp_policies: Vec<BgpPolicy>,
+    pub summaries: Vec<Summary>,
+    pub distances: Distances,
+    /// The IS-IS NET address, if the device runs IS-IS
+    pub isis_net: Option<String>,
+    pub static_routes: Vec<StaticRoute>,
+    pub default_route: Option<DefaultRoute>,
+    /// Whether the default route is advertised through RIP and OSPF
+    pub originate_default_route: bool,
+    pub ospf_router_id: Option<Ipv4Addr>,
+    /// The default gateway of a host, if not the router in its subnet
+    pub default_gateway: Option<IpAddr>,
+    pub dns_server: Option<IpAddr>,
+    pub services: Services,
+    /// Whether every OSPF process redistributes the routes of the others
+    pub ospf_mutual_redistribution: bool,
+    /// The hardware model of the device, which names its interfaces
+    /// after its real ports rather than after their number
+    pub platform: Option<Platform>,
+    /// How the ports are numbered, if not like the default ones of the kind.
+    /// The ports of a platform are always numbered like the real ones
+    pub numbering: Option<Numbering>,
+    /// The part the device plays in the network
+    pub role: Option<Role>,
+    /// The server the clock is synchronized with, if any
+    pub ntp_server: Option<IpAddr>,
+    /// Free-form labels, to select groups of devices
+    pub tags: Vec<String>,
+    next_iface: u8,
+}
+
+/// A route configured by hand on a device
+#[derive(Debug, PartialEq, Eq)]
+pub struct StaticRoute {
+    pub prefix: IpNet,
+    pub next_hop: IpAddr,
+    /// The administrative distance. A floating route has a distance
+    /// higher than the route it backs up
+    pub distance: Option<u8>,
+}
+
+/// A summary route, advertised instead of the more specific routes it covers
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Summary {
+    pub protocol: SummaryProtocol,
+    /// The summarized prefix. If `None`, the smallest prefix covering
+    /// the summarized subnets is used
+    pub prefix: Option<IpNet>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SummaryProtocol {
+    /// Summarize the subnets of an area, with `area N range`
+    Ospf { area: u16 },
+    /// Summarize the EIGRP subnets towards the given neighbor
+    Eigrp { towards: DefaultKey },
+    /// Summarize the RIP subnets towards the given neighbor
+    Rip { towards: DefaultKey },
+}
+
+impl Summary {
+    /// The prefix to advertise, given the subnets it should cover
+    fn prefix(&self, subnets: impl IntoIterator<Item = IpNet>) -> Option<IpNet> {
+        self.prefix.or_else(|| covering_prefix(subnets))
+    }
+}
+
+/// The attributes set on the routes exchanged with a BGP neighbor
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BgpPolicy {
+    pub neighbor: DefaultKey,
+    pub direction: Direction,
+    pub local_preference: Option<u32>,
+    /// The multi-exit discriminator
+    pub med: Option<u32>,
+    /// The community, in the `AS:NN` format
+    pub community: Option<String>,
+}
+
+/// The direction of the routes or packets a filter applies to
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+impl Direction {
+    fn keyword(self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+        }
+    }
+}
+
+/// A named list of prefixes, used to filter routes
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct PrefixList {
+    pub name: String,
+    pub entries: Vec<PrefixListEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct PrefixListEntry {
+    pub seq: u32,
+    #[serde(default)]
+    pub action: Action,
+    pub prefix: IpNet,
+    /// The minimum prefix length of the matched routes
+    pub ge: Option<u8>,
+    /// The maximum prefix length of the matched routes
+    pub le: Option<u8>,
+}
+
+/// A filter applied to the routes exchanged by a routing protocol
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct DistributeList {
+    pub protocol: FilteredProtocol,
+    pub direction: Direction,
+    pub filter: RouteFilter,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilteredProtocol {
+    Rip,
+    Ospf,
+    Eigrp,
+}
+
+/// What a distribute-list matches the routes against
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteFilter {
+    /// The name of a prefix-list of the device
+    PrefixList(String),
+    /// The name or number of an access list
+    Acl(String),
+}
+
+/// The gateway of last resort of a device
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DefaultRoute {
+    /// Send packets to the given address
+    NextHop(IpAddr),
+    /// Send packets out of the interface connected to the given device
+    ExitInterface(DefaultKey),
+}
+
+/// A link between routers.
+///
+/// `r1` must always be less than `r2`
+#[derive(Default)]
+pub struct Link {
+    /// The keys of the two devices
+    ends: (DefaultKey, DefaultKey),
+    r1: IpNet,
+    r2: IpNet,
+    r1_iface: u8,
+    r2_iface: u8,
+    r1_options: InterfaceOptions,
+    r2_options: InterfaceOptions,
+    /// The IPv6 addresses of the two sides, if the link is dual-stack
+    ipv6: Option<(IpNet, IpNet)>,
+    sublinks: Vec<SubLink>,
+    /// The interfaces of the parallel links bundled with this one
+    r1_members: Vec<u8>,
+    r2_members: Vec<u8>,
+    channel_group: Option<u16>,
+    ospf_area: Option<u16>,
+    /// The type of the interfaces on both sides of the link, set by
+    /// `LinkBuilder::interface_type`
+    interface_type: InterfaceType,
+    /// The OSPF cost of the interfaces on both sides of the link
+    pub ospf_cost: Option<u16>,
+    /// Whether OSPF MD5 authentication is enabled on this link only,
+    /// regardless of its area
+    pub ospf_auth: bool,
+    /// Whether the link's subnet is advertised by EIGRP
+    pub eigrp: bool,
+    /// Whether the link's subnet is advertised by BGP
+    pub bgp: bool,
+    /// The IS-IS level of the link, if it runs IS-IS
+    pub isis: Option<IsisLevel>,
+    /// Whether PIM sparse mode runs on both sides of the link
+    pub pim: bool,
+    /// The first-hop redundancy group shared by the two sides of the link
+    pub fhrp: Option<FhrpGroup>,
+    /// The BFD timers of both sides, if BFD detects failures of the link
+    pub bfd: Option<Bfd>,
+    /// The OSPF network type of both sides, overriding the one of the interface type
+    pub ospf_network_type: Option<OspfNetworkType>,
+    /// The ID of the OSPF process the link belongs to, if not the default one
+    pub ospf_process: Option<u16>,
+    /// The VLANs carried by the link, if it is a trunk
+    pub trunk: Option<Trunk>,
+    /// The VLAN of the switch ports of the link, if they are access ports
+    pub access_vlan: Option<u16>,
+    /// The VLAN of the IP phones behind the access ports of the link
+    pub voice_vlan: Option<u16>,
+    /// The PPP encapsulation of both sides, if the link is serial
+    pub ppp: Option<Ppp>,
+    /// The MTU of the interfaces on both sides, which OSPF neighbours must agree on
+    pub mtu: Option<u16>,
+    /// The bandwidth of the interfaces on both sides in kbit/s, unless set by a side
+    pub bandwidth: Option<u32>,
+    /// The delay of the interfaces on both sides in tens of microseconds,
+    /// unless set by a side
+    pub delay: Option<u32>,
+    /// Derive the interface identifiers of the IPv6 addresses of both sides
+    /// from their MAC address, leaving only the prefix to the link subnet
+    pub eui64: bool,
+}
+
+/// A link as seen from outside the crate, which can only be read
+#[derive(Clone, Copy)]
+pub struct LinkView<'a> {
+    link: &'a Link,
+}
+
+impl LinkView<'_> {
+    /// The addresses of the two sides, in the order of their keys
+    pub fn addresses(&self) -> (IpNet, IpNet) {
+        (self.link.r1, self.link.r2)
+    }
+
+    /// The interface numbers of the two sides, in the order of their keys
+    pub fn interfaces(&self) -> (u8, u8) {
+        (self.link.r1_iface, self.link.r2_iface)
+    }
+
+    pub fn ospf_area(&self) -> Option<u16> {
+        self.link.ospf_area
+    }
+
+    pub fn ospf_cost(&self) -> Option<u16> {
+        self.link.ospf_cost
+    }
+
+    pub fn interface_type(&self) -> InterfaceType {
+        self.link.interface_type
+    }
+}
+
+/// The addresses of the two sides of a link in one of the VLANs it carries
+#[derive(Debug)]
+struct SubLink {
+    vlan: u16,
+    r1: IpNet,
+    r2: IpNet,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DirectedLink {
+    close_key: DefaultKey,
+    far_key: DefaultKey,
+    close_ip: IpNet,
+    far_ip: IpNet,
+    /// The IPv6 addresses of a dual-stack link, whose `close_ip` and `far_ip` are IPv4
+    close_ipv6: Option<IpNet>,
+    far_ipv6: Option<IpNet>,
+    close_iface: u8,
+    far_iface: u8,
+    close_options: InterfaceOptions,
+    far_port_type: Option<PortType>,
+    /// The VLAN and address of every subinterface of the close side
+    close_subinterfaces: Vec<(u16, IpNet)>,
+    /// The name of the close interface, which is a port of the close platform
+    close_port: String,
+    close_members: Vec<u8>,
+    far_members: Vec<u8>,
+    /// The devices reached through the link: the far one, or the other
+    /// members if the link attaches the close one to a segment
+    far_routers: Vec<DefaultKey>,
+    channel_group: Option<u16>,
+    interface_type: InterfaceType,
+    ospf_area: Option<u16>,
+    ospf_cost: Option<u16>,
+    ospf_auth: bool,
+    eigrp: bool,
+    bgp: bool,
+    isis: Option<IsisLevel>,
+    pim: bool,
+    fhrp: Option<FhrpGroup>,
+    bfd: Option<Bfd>,
+    ospf_network_type: Option<OspfNetworkType>,
+    ospf_process: Option<u16>,
+    trunk: Option<Trunk>,
+    access_vlan: Option<u16>,
+    voice_vlan: Option<u16>,
+    ppp: Option<Ppp>,
+    mtu: Option<u16>,
+    bandwidth: Option<u32>,
+    delay: Option<u32>,
+    eui64: bool,
+}
+
+/// The type of the interfaces at the two sides of a link
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InterfaceType {
+    #[default]
+    Ethernet,
+    /// A serial WAN link between two routers, whose DCE side
+    /// provides the clock at `clock_rate` bit/s
+    Serial { dce: DefaultKey, clock_rate: u32 },
+    /// A Frame Relay PVC, carried by a cloud between serial interfaces
+    FrameRelay(FrameRelayPvc),
+}
+
+/// A Frame Relay PVC between a hub router and one of its spokes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FrameRelayPvc {
+    pub cloud: DefaultKey,
+    pub hub: DefaultKey,
+    /// The DLCI identifying the PVC at the hub side
+    pub hub_dlci: u16,
+    /// The DLCI identifying the PVC at the spoke side
+    pub spoke_dlci: u16,
+    /// The serial ports of the cloud connected to the hub and the spoke
+    hub_port: u8,
+    spoke_port: u8,
+}
+
+impl InterfaceType {
+    /// The name of the `iface`-th network interface of a device of the given kind,
+    /// which is of `port_type` if chosen, or else the default one for the link
+    fn iface_name(
+        self,
+        kind: DeviceKind,
+        numbering: &Numbering,
+        port_type: Option<PortType>,
+        iface: u8,
+    ) -> String {
+        let port_type = port_type.unwrap_or_else(|| match self {
+            InterfaceType::Ethernet => kind.port_type(),
+            InterfaceType::Serial { .. } | InterfaceType::FrameRelay(_) => {
+                assert_eq!(
+                    kind,
+                    DeviceKind::Router,
+                    "Serial links are only supported between routers"
+                );
+                PortType::Serial
+            }
+        });
+        port_type.iface_name(kind, numbering, iface)
+    }
+}
+
+impl DirectedLink {
+    pub fn close_key(&self) -> DefaultKey {
+        self.close_key
+    }
+
+    pub fn far_key(&self) -> DefaultKey {
+        self.far_key
+    }
+
+    /// The address of the close side, which is the one of a loopback if it's unnumbered
+    pub fn close_ip(&self) -> IpNet {
+        self.close_ip
+    }
+
+    pub fn far_ip(&self) -> IpNet {
+        self.far_ip
+    }
+
+    /// Whether the close side has an IPv4 address
+    fn has_ipv4(&self) -> bool {
+        self.close_ip.addr().is_ipv4()
+    }
+
+    /// Whether the close side has an IPv6 address, either as its only one
+    /// or besides an IPv4 one
+    fn has_ipv6(&self) -> bool {
+        self.close_ip.addr().is_ipv6() || self.close_ipv6.is_some()
+    }
+
+    /// The name of the close interface of the link, which is a
+    /// subinterface at the hub of a Frame Relay PVC
+    fn iface_name(&self) -> String {
+        match self.interface_type {
+            InterfaceType::FrameRelay(pvc) if pvc.hub == self.close_key => {
+                format!("{}.{}", self.close_port, pvc.hub_dlci)
+            }
+            _ => self.close_port.clone(),
+        }
+    }
+}
+
+/// The PPP encapsulation of a serial link
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Ppp {
+    /// How the routers authenticate each other, if they do
+    pub authentication: Option<PppAuthentication>,
+    /// The password shared by the two routers, which know each other by their hostnames
+    pub password: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PppAuthentication {
+    Chap,
+    Pap,
+}
+
+/// The IPv6 autoconfiguration offered by a router to the hosts of a LAN
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Ipv6Autoconfig {
+    pub mode: Ipv6AddressingMode,
+    /// The DNS server given to the hosts by DHCPv6
+    pub dns_server: Option<Ipv6Addr>,
+    /// The domain name given to the hosts by DHCPv6
+    pub domain_name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ipv6AddressingMode {
+    /// The hosts generate their addresses from the advertised prefix
+    Slaac,
+    /// The hosts generate their addresses, and get the other settings from DHCPv6
+    Stateless,
+    /// The hosts get their addresses and the other settings from DHCPv6
+    Stateful,
+}
+
+/// The options which only apply to one side of a link
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct InterfaceOptions {
+    /// The description of the interface, instead of the generated one
+    pub description: Option<String>,
+    /// Don't send RIP updates out of this interface
+    pub rip_passive: bool,
+    /// The key chain used to authenticate RIP updates
+    pub rip_key_chain: Option<String>,
+    /// The route-map used to route the packets received by this interface
+    pub policy_route_map: Option<String>,
+    /// The VRF the interface belongs to, instead of the global routing table
+    pub vrf: Option<String>,
+    /// The priority of this side, if the link has a first-hop redundancy group
+    pub fhrp_priority: Option<u8>,
+    /// Take over as active router when having a higher priority
+    pub fhrp_preempt: bool,
+    pub fhrp_track: Option<FhrpTrack>,
+    /// The GLBP weight of this side, used by weighted load balancing
+    pub glbp_weighting: Option<u8>,
+    /// The bandwidth of the interface in kbit/s, as used by the routing metrics
+    pub bandwidth: Option<u32>,
+    /// The delay of the interface in tens of microseconds
+    pub delay: Option<u32>,
+    /// Use the port of a multilayer switch as a routed port, instead of a switchport
+    pub routed: bool,
+    /// The access list filtering the packets received by the interface
+    pub acl_in: Option<String>,
+    /// The access list filtering the packets sent by the interface
+    pub acl_out: Option<String>,
+    /// The policy-map applied to the packets received by the interface
+    pub service_policy_in: Option<String>,
+    /// The policy-map applied to the packets sent by the interface
+    pub service_policy_out: Option<String>,
+    /// Where the DHCP requests received by the interface are forwarded
+    pub dhcp_relay: Option<DhcpRelay>,
+    /// The MAC addresses allowed on the port, if it is a secure access port
+    pub port_security: Option<PortSecurity>,
+    /// The loopback whose address the interface borrows, instead of having its own
+    pub unnumbered: Option<u8>,
+    /// Whether the interface sends and receives CDP, overriding the device
+    pub cdp: Option<bool>,
+    /// Whether the interface sends and receives LLDP, overriding the device
+    pub lldp: Option<bool>,
+    /// How the hosts behind the interface configure their IPv6 addresses
+    pub ipv6_autoconfig: Option<Ipv6Autoconfig>,
+    /// The type of the port, instead of the default one of the device and link
+    pub port_type: Option<PortType>,
+    /// Leave the interface administratively down
+    pub shutdown: bool,
+}
+
+/// The type of a port, which decides the name of its interface
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PortType {
+    GigabitEthernet,
+    FastEthernet,
+    Serial,
+    /// A virtual interface, numbered like the ports of the device: it
+    /// mustn't have the same number as one of the device's loopbacks
+    Loopback,
+}
+
+impl PortType {
+    /// The name of the `iface`-th network interface of a device of the given
+    /// kind, whose ports are numbered by `numbering`
+    fn iface_name(self, kind: DeviceKind, numbering: &Numbering, iface: u8) -> String {
+        let prefix = match self {
+            PortType::GigabitEthernet => "GigabitEthernet",
+            PortType::FastEthernet => "FastEthernet",
+            PortType::Serial => "Serial",
+            PortType::Loopback => return format!("Loopback{iface}"),
+        };
+        let number = numbering.number(iface);
+        match kind {
+            DeviceKind::Router | DeviceKind::Switch | DeviceKind::MultilayerSwitch => {
+                format!("{prefix} {number}")
+            }
+            DeviceKind::Pc
+            | DeviceKind::Laptop
+            | DeviceKind::Server
+            | DeviceKind::FrameRelayCloud => format!("{prefix}{number}"),
+        }
+    }
+}
+
+/// How the ports of a device are numbered, to match the cabling of an existing
+/// Packet Tracer file
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Numbering {
+    /// The slot/port pattern, where `{n}` is replaced by the port number
+    pub pattern: String,
+    /// The number of the first port
+    #[serde(default)]
+    pub start: u8,
+}
+
+impl Numbering {
+    pub fn new(pattern: &str, start: u8) -> Self {
+        Numbering {
+            pattern: pattern.to_string(),
+            start,
+        }
+    }
+
+    /// The number of the `iface`-th port
+    fn number(&self, iface: u8) -> String {
+        let number = u16::from(self.start) + u16::from(iface);
+        self.pattern.replace("{n}", &number.to_string())
+    }
+}
+
+/// A hardware model of Packet Tracer, whose ports are numbered
+/// by slot rather than one per link
+///
+/// The serial ports of the routers are those of two HWIC-2T modules
+/// (or NIM-2T on the 4331), which must be added to the device by hand
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+pub enum Platform {
+    #[serde(rename = "1941")]
+    Isr1941,
+    #[serde(rename = "2911")]
+    Isr2911,
+    #[serde(rename = "4331")]
+    Isr4331,
+    #[serde(rename = "2960")]
+    Catalyst2960,
+    #[serde(rename = "3560")]
+    Catalyst3560,
+}
+
+impl Platform {
+    /// The type of the ports used by links of `interface_type`,
+    /// unless the port type was chosen
+    fn port_type(self, interface_type: InterfaceType, port_type: Option<PortType>) -> PortType {
+        match (port_type, interface_type) {
+            (Some(port_type), _) => port_type,
+            (None, InterfaceType::Serial { .. } | InterfaceType::FrameRelay(_)) => PortType::Serial,
+            (None, InterfaceType::Ethernet) => match self {
+                Platform::Isr1941 | Platform::Isr2911 | Platform::Isr4331 => {
+                    PortType::GigabitEthernet
+                }
+                Platform::Catalyst2960 | Platform::Catalyst3560 => PortType::FastEthernet,
+            },
+        }
+    }
+
+    /// The name of the `index`-th port of the given type, if the platform has it
+    fn port_name(self, port_type: PortType, index: u8) -> Option<String> {
+        let (slot, port) = (index / 2, index % 2);
+        let name = match (self, port_type) {
+            (Platform::Isr1941, PortType::GigabitEthernet) if index < 2 => {
+                format!("GigabitEthernet 0/{index}")
+            }
+            (Platform::Isr2911, PortType::GigabitEthernet) if index < 3 => {
+                format!("GigabitEthernet 0/{index}")
+            }
+            (Platform::Isr1941 | Platform::Isr2911, PortType::Serial) if index < 4 => {
+                format!("Serial 0/{slot}/{port}")
+            }
+            (Platform::Isr4331, PortType::GigabitEthernet) if index < 3 => {
+                format!("GigabitEthernet 0/0/{index}")
+            }
+            (Platform::Isr4331, PortType::Serial) if index < 4 => {
+                format!("Serial 0/{}/{port}", slot + 1)
+            }
+            (Platform::Catalyst2960 | Platform::Catalyst3560, PortType::FastEthernet)
+                if index < 24 =>
+            {
+                format!("FastEthernet 0/{}", index + 1)
+            }
+            (Platform::Catalyst2960 | Platform::Catalyst3560, PortType::GigabitEthernet)
+                if index < 2 =>
+            {
+                format!("GigabitEthernet 0/{}", index + 1)
+            }
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Platform::Isr1941 => "1941",
+            Platform::Isr2911 => "2911",
+            Platform::Isr4331 => "4331",
+            Platform::Catalyst2960 => "2960",
+            Platform::Catalyst3560 => "3560",
+        })
+    }
+}
+
+/// A first-hop redundancy group, shared by the routers of a LAN
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct FhrpGroup {
+    #[serde(default)]
+    pub protocol: FhrpProtocol,
+    pub group: u16,
+    pub virtual_ip: IpAddr,
+    /// How GLBP distributes the hosts between the gateways
+    pub load_balancing: Option<GlbpLoadBalancing>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FhrpProtocol {
+    #[default]
+    Hsrp,
+    Vrrp,
+    Glbp,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GlbpLoadBalancing {
+    RoundRobin,
+    Weighted,
+    HostDependent,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OspfNetworkType {
+    PointToPoint,
+    Broadcast,
+    NonBroadcast,
+}
+
+/// The timers of a BFD session
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+pub struct Bfd {
+    /// The transmit interval, in milliseconds
+    pub interval: u16,
+    /// The receive interval, in milliseconds
+    pub min_rx: u16,
+    /// How many missed packets make the session go down
+    pub multiplier: u8,
+}
+
+/// The DHCP server a relay forwards requests to
+///
+/// In the YAML, either an address or the name of a device
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DhcpRelay {
+    Address(IpAddr),
+    Device(String),
+}
+
+/// The limits on the MAC addresses which can use an access port
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct PortSecurity {
+    /// How many MAC addresses can be learned, if not just one
+    pub maximum: Option<u16>,
+    pub violation: Option<PortSecurityViolation>,
+    /// Save the learned MAC addresses in the running configuration
+    pub sticky: bool,
+}
+
+/// What a secure port does with frames from too many MAC addresses
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PortSecurityViolation {
+    /// Drop the frames silently
+    Protect,
+    /// Drop the frames and log the violation
+    Restrict,
+    /// Put the port in the error-disabled state
+    Shutdown,
+}
+
+/// An interface whose state changes the first-hop redundancy priority
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct FhrpTrack {
+    /// The name of the device the tracked interface is connected to
+    pub towards: String,
+    /// How much the priority decreases when the interface goes down
+    pub decrement: u8,
+}
+
+/// The services provided by a server
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Services {
+    pub http: bool,
+    pub tftp: bool,
+    /// The records of the DNS service, which is off if there are none
+    pub dns_records: Vec<DnsRecord>,
+    /// The pools of the DHCP service, which is off if there are none
+    pub dhcp_pools: Vec<DhcpPool>,
+}
+
+/// An A record, resolving `name` to `address`
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct DnsRecord {
+    pub name: String,
+    pub address: IpAddr,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct DhcpPool {
+    pub name: String,
+    /// The first address leased, with the prefix length of its subnet
+    pub start: Ipv4Net,
+    pub max_users: u16,
+    pub default_gateway: Option<Ipv4Addr>,
+    pub dns_server: Option<Ipv4Addr>,
+}
+
+/// A multi-access LAN, where the members share the subnet through
+/// their links to a switch
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    pub switch: DefaultKey,
+    pub subnet: IpNet,
+    pub members: Vec<DefaultKey>,
+}
+
+/// A group of devices, such as a branch office, which share its defaults
+#[derive(Debug)]
+pub struct Site {
+    pub name: String,
+    pub members: Vec<DefaultKey>,
+    /// The domain name of the members which don't set their own
+    pub domain_name: Option<String>,
+    /// The NTP server of the members which don't set their own
+    pub ntp_server: Option<IpAddr>,
+    /// The block the subnets of the links inside the site are allocated
+    /// from, instead of the address pool of the network
+    address_pool: Option<AddressPool>,
+}
+
+impl Site {
+    pub fn new(name: &str, members: &[DefaultKey]) -> Self {
+        Site {
+            name: name.to_string(),
+            members: members.to_vec(),
+            domain_name: None,
+            ntp_server: None,
+            address_pool: None,
+        }
+    }
+
+    pub fn domain_name(mut self, domain_name: Option<String>) -> Self {
+        self.domain_name = domain_name;
+        self
+    }
+
+    pub fn ntp_server(mut self, ntp_server: Option<IpAddr>) -> Self {
+        self.ntp_server = ntp_server;
+        self
+    }
+
+    /// Carve the subnets of the links inside the site out of `pool`,
+    /// each with a prefix of `prefix_len` bits
+    pub fn address_pool(mut self, pool: &str, prefix_len: u8) -> Self {
+        let pool = IpNet::from_str(pool).unwrap_or_else(|_| panic!("Failed to parse ip: {pool}"));
+        self.address_pool = Some(AddressPool::new(pool, prefix_len));
+        self
+    }
+}
+
+/// A site-to-site IPsec VPN between two routers
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Vpn {
+    pub pre_shared_key: String,
+    pub peers: [VpnPeer; 2],
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VpnPeer {
+    pub router: DefaultKey,
+    /// The neighbour the tunnel is sent to, which decides the outside interface
+    pub outside: DefaultKey,
+    /// The networks behind the router, whose traffic goes through the tunnel
+    pub networks: Vec<IpNet>,
+}
+
+/// A problem in the topology, found by [`App::validate`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValidationIssue {
+    pub code: IssueCode,
+    pub severity: Severity,
+    /// The names of the devices the issue is about, if it's about specific ones
+    pub devices: Vec<String>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn warning<'a>(
+        code: IssueCode,
+        devices: impl IntoIterator<Item = &'a Device>,
+        message: String,
+    ) -> Self {
+        Self::new(code, Severity::Warning, devices, message)
+    }
+
+    fn error<'a>(
+        code: IssueCode,
+        devices: impl IntoIterator<Item = &'a Device>,
+        message: String,
+    ) -> Self {
+        Self::new(code, Severity::Error, devices, message)
+    }
+
+    fn new<'a>(
+        code: IssueCode,
+        severity: Severity,
+        devices: impl IntoIterator<Item = &'a Device>,
+        message: String,
+    ) -> Self {
+        let mut devices: Vec<String> = devices.into_iter().map(|dev| dev.name.clone()).collect();
+        devices.sort();
+        devices.dedup();
+        ValidationIssue {
+            code,
+            severity,
+            devices,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        };
+        write!(f, "{severity} [{}]: {}", self.code.as_str(), self.message)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    /// The configurations can be generated, but may not work as intended
+    Warning,
+    /// The configurations would be broken
+    Error,
+}
+
+/// What kind of problem a `ValidationIssue` is
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IssueCode {
+    NoInterfaces,
+    IsolatedDevice,
+    Partitioned,
+    RipWithoutNeighbors,
+    MissingBackboneArea,
+    RouteReflector,
+    FloatingRoute,
+    AddressConflict,
+    VtpPasswordMismatch,
+    /// A device sends to a server which doesn't exist or has no address
+    UnreachableServer,
+    /// An SSH server has more VTY lines than IOS allows, or none
+    VtyLines,
+    /// An unnumbered interface borrows the address of a missing loopback
+    UnnumberedWithoutLoopback,
+}
+
+impl IssueCode {
+    /// The stable identifier of the code, for tools parsing the issues
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IssueCode::NoInterfaces => "no-interfaces",
+            IssueCode::IsolatedDevice => "isolated-device",
+            IssueCode::Partitioned => "partitioned",
+            IssueCode::RipWithoutNeighbors => "rip-without-neighbors",
+            IssueCode::MissingBackboneArea => "missing-backbone-area",
+            IssueCode::RouteReflector => "route-reflector",
+            IssueCode::FloatingRoute => "floating-route",
+            IssueCode::AddressConflict => "address-conflict",
+            IssueCode::VtpPasswordMismatch => "vtp-password-mismatch",
+            IssueCode::UnreachableServer => "unreachable-server",
+            IssueCode::VtyLines => "vty-lines",
+            IssueCode::UnnumberedWithoutLoopback => "unnumbered-without-loopback",
+        }
+    }
+}
+
+/// The constraints of a randomly generated topology, whose routers are
+/// named `R1`, `R2` and so on, and get their subnets from `10.0.0.0/16`
+///
+/// The same constraints always generate the same topology
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct RandomTopology {
+    pub seed: u64,
+    pub devices: usize,
+    /// The number of links of each router is drawn between
+    /// `min_degree` and `max_degree`
+    pub min_degree: usize,
+    pub max_degree: usize,
+    /// The protocols the topology can be routed with, each with its
+    /// weight: one of them is drawn, and run on every router
+    pub protocols: Vec<(RoutingProtocol, u32)>,
+}
+
+impl Default for RandomTopology {
+    fn default() -> Self {
+        RandomTopology {
+            seed: 0,
+            devices: 6,
+            min_degree: 1,
+            max_degree: 3,
+            protocols: vec![(RoutingProtocol::Ospf, 1)],
+        }
+    }
+}
+
+impl RandomTopology {
+    /// Generate a connected topology satisfying the constraints
+    pub fn generate(&self) -> App {
+        random::generate(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingProtocol {
+    Rip,
+    Ospf,
+    Eigrp,
+}
+
+/// The configuration generated for a device
+#[derive(Debug, PartialEq, Eq)]
+pub enum Config {
+    /// The commands to enter in the CLI of a router or switch
+    Cli(String),
+    /// The settings to enter in the desktop of an end host,
+    /// or in the config tab of a Frame Relay cloud
+    HostSettings(String),
+}
+
+impl Deref for Config {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Config::Cli(commands) => commands,
+            Config::HostSettings(settings) => settings,
+        }
+    }
+}
+
+/// Everything needed to generate the configuration of a device
+pub(crate) struct DeviceContext<'a> {
+    app: &'a App,
+    key: DefaultKey,
+    device: &'a Device,
+    /// The links of the device, ordered by interface number
+    links: Vec<DirectedLink>,
+}
+
+impl DeviceContext<'_> {
+    /// Write the description of the interface connected to `far_iface`
+    /// of the far device of `link`
+    fn write_description(&self, res: &mut String, link: &DirectedLink, far_iface: u8) {
+        if let Some(description) = &link.close_options.description {
+            writeln!(res, "   description {description}").unwrap();
+        } else {
+            let far = &self.app.devices[link.far_key];
+            let far_iface = self.app.port_name(
+                link.far_key,
+                link.interface_type,
+                link.far_port_type,
+                far_iface,
+            );
+            writeln!(res, "   description Link to {} ({far_iface})", far.name).unwrap();
+        }
+    }
+
+    /// Whether any interface of the device has an IPv6 address
+    fn has_ipv6(&self) -> bool {
+        let links = self.links.iter().flat_map(|link| {
+            let subinterfaces = link.close_subinterfaces.iter().map(|(_, ip)| ip);
+            std::iter::once(&link.close_ip)
+                .chain(&link.close_ipv6)
+                .chain(subinterfaces)
+        });
+        let svis = self.device.svis.iter().map(|svi| &svi.ip);
+        links
+            .chain(&self.device.loopbacks)
+            .chain(svis)
+            .any(|ip| ip.addr().is_ipv6())
+    }
+
+    /// The same context, restricted to the links in the given VRF
+    fn in_vrf(&self, vrf: Option<&str>) -> Self {
+        DeviceContext {
+            app: self.app,
+            key: self.key,
+            device: self.device,
+            links: self
+                .app
+                .links_of(self.key)
+                .into_iter()
+                .filter(|link| link.close_options.vrf.as_deref() == vrf)
+                .collect(),
+        }
+    }
+}
+
+/// Write the static routes and the routing processes of a device
+fn write_routing(ctx: &DeviceContext, res: &mut String) {
+    let device = ctx.device;
+
+    // Static routes
+    for route in &device.static_routes {
+        write!(
+            res,
+            "ip route {} {} {}",
+            route.prefix.network(),
+            route.prefix.netmask(),
+            route.next_hop,
+        )
+        .unwrap();
+        if let Some(distance) = route.distance {
+            write!(res, " {distance}").unwrap();
+        }
+        res.push('\n');
+    }
+    match device.default_route {
+        Some(DefaultRoute::NextHop(next_hop)) => {
+            writeln!(res, "ip route 0.0.0.0 0.0.0.0 {next_hop}").unwrap();
+        }
+        Some(DefaultRoute::ExitInterface(far_key)) => {
+            let link = ctx
+                .links
+                .iter()
+                .find(|link| link.far_key == far_key)
+                .expect("The default route must exit towards a neighbour");
+            writeln!(res, "ip route 0.0.0.0 0.0.0.0 {}", link.iface_name()).unwrap();
+        }
+        None => {}
+    }
+    if !device.static_routes.is_empty() || device.default_route.is_some() {
+        res.push('\n');
+    }
+
+    // Routing processes only see the links of their VRF
+    let global = ctx.in_vrf(None);
+    protocols::rip::write_process(&global, res);
+    protocols::ospf::write_process(&global, res);
+    protocols::eigrp::write_process(&global, res);
+    protocols::isis::write_process(&global, res);
+    protocols::bgp::write_process(&global, res);
+}
+
+/// The part a device plays in a hierarchical network
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Core,
+    Distribution,
+    Access,
+    /// The routers connecting the network to the outside
+    Edge,
+    Host,
+}
+
+/// The settings shared by the devices with a role or tag
+///
+/// The lists are added to the ones of each device, except for the entries
+/// with the name of one the device already has, while the other settings
+/// are only used by the devices which don't set their own
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct DeviceDefaults {
+    pub acls: Vec<Acl>,
+    pub users: Vec<LocalUser>,
+    pub banner: Option<String>,
+    pub domain_name: Option<String>,
+    pub ntp_server: Option<IpAddr>,
+    pub logging: Option<Logging>,
+    pub snmp: Option<Snmp>,
+    pub ssh: Option<Ssh>,
+    pub cdp: Option<bool>,
+    pub lldp: Option<bool>,
+}
+
+impl DeviceDefaults {
+    fn merge_into(&self, device: &mut Device) {
+        for acl in &self.acls {
+            if !device.acls.iter().any(|other| other.name == acl.name) {
+                device.acls.push(acl.clone());
+            }
+        }
+        for user in &self.users {
+            if !device.users.iter().any(|other| other.name == user.name) {
+                device.users.push(user.clone());
+            }
+        }
+        fill(&mut device.banner, &self.banner);
+        fill(&mut device.dns.domain_name, &self.domain_name);
+        fill(&mut device.ntp_server, &self.ntp_server);
+        fill(&mut device.logging, &self.logging);
+        fill(&mut device.snmp, &self.snmp);
+        fill(&mut device.ssh, &self.ssh);
+        fill(&mut device.cdp, &self.cdp);
+        fill(&mut device.lldp, &self.lldp);
+    }
+}
+
+/// Set `setting` to `default`, unless it's already set
+fn fill<T: Clone>(setting: &mut Option<T>, default: &Option<T>) {
+    if setting.is_none() {
+        setting.clone_from(default);
+    }
+}
+
+/// The kind of a device, which decides the commands it understands
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeviceKind {
+    #[default]
+    Router,
+    /// A layer 2 switch, whose ports carry VLANs and don't route
+    Switch,
+    /// A switch which can also route between its ports
+    MultilayerSwitch,
+    /// The end hosts, which are configured from their desktop rather than the CLI
+    Pc,
+    Laptop,
+    /// An end host which also provides `Device::services` to the others
+    Server,
+    /// The cloud switching the PVCs of a Frame Relay network
+    FrameRelayCloud,
+}
+
+impl DeviceKind {
+    /// The name of the `iface`-th network interface of a device of this kind
+    fn iface_name(self, iface: u8) -> String {
+        self.port_type().iface_name(self, &self.numbering(), iface)
+    }
+
+    /// The type of the default ports of the kind
+    fn port_type(self) -> PortType {
+        match self {
+            DeviceKind::Router => PortType::GigabitEthernet,
+            DeviceKind::Switch
+            | DeviceKind::MultilayerSwitch
+            | DeviceKind::Pc
+            | DeviceKind::Laptop
+            | DeviceKind::Server => PortType::FastEthernet,
+            DeviceKind::FrameRelayCloud => PortType::Serial,
+        }
+    }
+
+    /// How the ports of the kind are numbered, unless chosen for the device
+    fn numbering(self) -> Numbering {
+        match self {
+            DeviceKind::Router => Numbering::new("{n}/0", 0),
+            DeviceKind::Switch | DeviceKind::MultilayerSwitch => Numbering::new("0/{n}", 1),
+            DeviceKind::Pc
+            | DeviceKind::Laptop
+            | DeviceKind::Server
+            | DeviceKind::FrameRelayCloud => Numbering::new("{n}", 0),
+        }
+    }
+
+    /// Whether devices of this kind are end hosts with a single address
+    fn is_host(self) -> bool {
+        matches!(
+            self,
+            DeviceKind::Pc | DeviceKind::Laptop | DeviceKind::Server
+        )
+    }
+
+    /// Whether devices of this kind have a routing table
+    fn routes(self) -> bool {
+        matches!(self, DeviceKind::Router | DeviceKind::MultilayerSwitch)
+    }
+}
+
+impl DistributeList {
+    /// Write the distribute-lists of a device which apply to `protocol`
+    fn write_all(res: &mut String, device: &Device, protocol: FilteredProtocol) {
+        for list in &device.distribute_lists {
+            if list.protocol != protocol {
+                continue;
+            }
+            let direction = list.direction.keyword();
+            match &list.filter {
+                RouteFilter::PrefixList(name) => {
+                    writeln!(res, "   distribute-list prefix {name} {direction}").unwrap()
+                }
+                RouteFilter::Acl(acl) => {
+                    writeln!(res, "   distribute-list {acl} {direction}").unwrap()
+                }
+            }
+        }
+    }
+}
+
+/// The smallest prefix containing all the given subnets
+fn covering_prefix(subnets: impl IntoIterator<Item = IpNet>) -> Option<IpNet> {
+    let mut subnets = subnets.into_iter();
+    let mut prefix = subnets.next()?.trunc();
+
+    for subnet in subnets {
+        while !prefix.contains(&subnet) {
+            prefix = prefix.supernet()?;
+        }
+    }
+
+    Some(prefix)
+}
+
+/// The key of a map in a JSON or TOML document, which is a number when it
+/// looks like one, as in YAML, so that the maps keyed by VLAN or area load
+fn document_key(key: String) -> serde_yaml::Value {
+    match key.parse::<i64>() {
+        Ok(number) if number.to_string() == key => serde_yaml::Value::Number(number.into()),
+        _ => serde_yaml::Value::String(key),
+    }
+}
+
+/// The name of a device, without the characters IOS doesn't allow in hostnames
+fn hostname(name: &str) -> String {
+    let hostname: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(63)
+        .collect();
+    hostname.trim_matches('-').to_string()
+}
+
+/// Write the address of an interface, in the syntax of its family
+fn write_address(res: &mut String, ip: IpNet) {
+    match ip {
+        IpNet::V4(ip) => writeln!(res, "   ip address {} {}", ip.addr(), ip.netmask()).unwrap(),
+        IpNet::V6(ip) => writeln!(res, "   ipv6 address {ip}\n   ipv6 enable").unwrap(),
+    }
+}
+
+/// Write an address of the close side of a link, of which only the prefix
+/// is kept if the IPv6 addresses use EUI-64
+fn write_link_address(res: &mut String, link: &DirectedLink, ip: IpNet) {
+    match ip {
+        IpNet::V6(ip) if link.eui64 => {
+            writeln!(res, "   ipv6 address {} eui-64\n   ipv6 enable", ip.trunc()).unwrap()
+        }
+        _ => write_address(res, ip),
+    }
+}
+
+/// Enable the close interface of a link, unless it must start administratively down
+fn write_shutdown(res: &mut String, link: &DirectedLink) {
+    if link.close_options.shutdown {
+        res.push_str("   shutdown\n");
+    } else {
+        res.push_str("   no shutdown\n");
+    }
+}
+
+/// Parse the subnet of a link, and pick the addresses of its two sides
+fn link_addresses(ip: &str) -> (IpNet, IpNet) {
+    let ip = IpNet::from_str(ip).unwrap_or_else(|_| panic!("Failed to parse ip: {ip}"));
+    // Point-to-point subnets have no network nor broadcast address (RFC 3021, RFC 6164)
+    if ip.prefix_len() + 1 == ip.max_prefix_len() {
+        let r1 = to_ipnet(ip.network(), ip.prefix_len());
+        let r2 = to_ipnet(ip.broadcast(), ip.prefix_len());
+        return (r1, r2);
+    }
+    // IPv6 has no broadcast, but the first address is the subnet-router anycast
+    let mut hosts = ip.hosts().skip(usize::from(ip.addr().is_ipv6()));
+    let (Some(r1), Some(r2)) = (hosts.next(), hosts.next()) else {
+        panic!("The subnet {ip} has less than two addresses");
+    };
+    let r1 = to_ipnet(r1, ip.prefix_len());
+    let r2 = to_ipnet(r2, ip.prefix_len());
+    (r1, r2)
+}
+
+/// Give the sides of a link the addresses chosen for them, and the others
+/// the first of the default `addresses` which isn't taken
+fn choose_addresses(
+    addresses: (IpNet, IpNet),
+    chosen: (Option<IpAddr>, Option<IpAddr>),
+) -> (IpNet, IpNet) {
+    let subnet = addresses.0.trunc();
+    let point_to_point = subnet.prefix_len() + 1 == subnet.max_prefix_len();
+    for ip in [chosen.0, chosen.1].into_iter().flatten() {
+        assert!(
+            subnet.contains(&ip),
+            "The address {ip} is outside of {subnet}"
+        );
+        let reserved = ip == subnet.network() || (ip.is_ipv4() && ip == subnet.broadcast());
+        assert!(
+            point_to_point || !reserved,
+            "The address {ip} can't be given to an interface of {subnet}"
+        );
+    }
+
+    let defaults = [addresses.0.addr(), addresses.1.addr()];
+    let r1 = chosen.0.unwrap_or_else(|| {
+        defaults
+            .into_iter()
+            .find(|&ip| Some(ip) != chosen.1)
+            .unwrap()
+    });
+    let r2 = chosen
+        .1
+        .unwrap_or_else(|| defaults.into_iter().find(|&ip| ip != r1).unwrap());
+    assert_ne!(
+        r1, r2,
+        "The two sides of a link can't both have the address {r1}"
+    );
+    (
+        to_ipnet(r1, subnet.prefix_len()),
+        to_ipnet(r2, subnet.prefix_len()),
+    )
+}
+
+/// Convert an `IpAddr` to an `IpNet` with the given prefix length
+fn to_ipnet(ip: IpAddr, cidr: u8) -> IpNet {
+    match ip {
+        IpAddr::V4(ipv4) => IpNet::V4(Ipv4Net::new(ipv4, cidr).unwrap()),
+        IpAddr::V6(ipv6) => IpNet::V6(Ipv6Net::new(ipv6, cidr).unwrap()),
+    }
+}
+
+/// The type of an OSPF area, which determines the LSAs allowed into it
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AreaType {
+    Stub,
+    TotallyStubby,
+    Nssa,
+    TotallyNssa,
+}
+
+/// The IS-IS adjacencies formed over a link
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+pub enum IsisLevel {
+    #[serde(rename = "level-1")]
+    Level1,
+    #[serde(rename = "level-2")]
+    Level2,
+    #[serde(rename = "level-1-2")]
+    Level1And2,
+}
+
+/// How a device resolves names
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct DnsSettings {
+    pub domain_name: Option<String>,
+    pub name_servers: Vec<IpAddr>,
+    /// Whether mistyped commands are resolved as hostnames, if not the default
+    pub domain_lookup: Option<bool>,
+}
+
+/// The syslog server of a device
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Logging {
+    /// The name of the device which collects the messages
+    pub server: String,
+    /// The least severe messages sent to the server
+    pub level: Option<SyslogLevel>,
+    /// Whether messages are timestamped
+    #[serde(default)]
+    pub timestamps: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogLevel {
+    Emergencies,
+    Alerts,
+    Critical,
+    Errors,
+    Warnings,
+    Notifications,
+    Informational,
+    Debugging,
+}
+
+impl SyslogLevel {
+    fn keyword(self) -> &'static str {
+        match self {
+            SyslogLevel::Emergencies => "emergencies",
+            SyslogLevel::Alerts => "alerts",
+            SyslogLevel::Critical => "critical",
+            SyslogLevel::Errors => "errors",
+            SyslogLevel::Warnings => "warnings",
+            SyslogLevel::Notifications => "notifications",
+            SyslogLevel::Informational => "informational",
+            SyslogLevel::Debugging => "debugging",
+        }
+    }
+}
+
+/// The passwords protecting the access to a device
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Passwords {
+    pub enable_secret: Option<String>,
+    pub console: Option<String>,
+    pub vty: Option<String>,
+}
+
+/// A user of the local database of a device
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct LocalUser {
+    pub name: String,
+    pub privilege: Option<u8>,
+    pub secret: String,
+}
+
+/// The method lists of the AAA model
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Aaa {
+    /// How users logging in are authenticated
+    pub login: Option<AaaMethodList>,
+    /// Whether authenticated users can start a shell
+    pub exec: Option<AaaMethodList>,
+}
+
+/// The methods tried in order by a list, which is the default one if it has no name
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct AaaMethodList {
+    pub name: Option<String>,
+    pub methods: Vec<AaaMethod>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AaaMethod {
+    /// The local user database
+    Local,
+    /// The enable password
+    Enable,
+    /// Allow any user
+    None,
+}
+
+/// The SSH server of a device, which authenticates the local users
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Ssh {
+    /// The domain of the RSA keys, if not the one of `Device::dns`
+    pub domain_name: Option<String>,
+    /// The size of the RSA keys in bits
+    pub modulus: u16,
+    /// How many VTY lines accept SSH connections, between 1 and 16
+    pub vty_lines: u8,
+}
+
+impl Default for Ssh {
+    fn default() -> Self {
+        Ssh {
+            domain_name: None,
+            modulus: 1024,
+            vty_lines: 5,
+        }
+    }
+}
+
+impl Ssh {
+    /// The number of the last VTY line accepting SSH connections, which
+    /// is the first one if there are none, as reported by `App::validate`
+    pub fn last_vty_line(&self) -> u8 {
+        self.vty_lines.saturating_sub(1)
+    }
+}
+
+/// The SNMP agent of a device
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Snmp {
+    pub location: Option<String>,
+    pub contact: Option<String>,
+    /// The SNMPv2c communities
+    pub communities: Vec<SnmpCommunity>,
+    /// The SNMPv3 users
+    pub users: Vec<SnmpUser>,
+    /// The SNMPv2c managers traps are sent to
+    pub trap_hosts: Vec<SnmpTrapHost>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct SnmpCommunity {
+    pub name: String,
+    pub access: SnmpAccess,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpAccess {
+    /// Read-only
+    Ro,
+    /// Read-write
+    Rw,
+}
+
+/// An SNMPv3 user, which encrypts its messages if it has a `priv_password`
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct SnmpUser {
+    pub name: String,
+    pub group: String,
+    pub auth_password: String,
+    pub priv_password: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct SnmpTrapHost {
+    /// The name of the device which receives the traps
+    pub server: String,
+    pub community: String,
+}
+
+/// The settings of the RIP process of a device
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RipSettings {
+    pub version: RipVersion,
+    /// Whether networks are summarized at classful boundaries
+    pub auto_summary: bool,
+}
+
+impl Default for RipSettings {
+    fn default() -> Self {
+        RipSettings {
+            version: RipVersion::V2,
+            auto_summary: true,
+        }
+    }
+}
+
+/// A named set of keys, used to authenticate routing updates
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct KeyChain {
+    pub name: String,
+    /// The key strings, indexed by key ID
+    pub keys: BTreeMap<u32, String>,
+}
+
+/// A separate routing table of a device
+///
+/// RIP and EIGRP route a VRF in an address family of their process, while
+/// OSPF uses a process per VRF, numbered from 2 in the order of `Device::vrfs`
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Vrf {
+    pub name: String,
+    /// The route distinguisher
+    pub rd: Option<String>,
+}
+
+/// A VLAN of a switch
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Vlan {
+    pub id: u16,
+    pub name: Option<String>,
+}
+
+/// The layer 3 interface of a switch in a VLAN
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Svi {
+    pub vlan: u16,
+    /// The address of the interface, with the prefix length of its subnet
+    pub ip: IpNet,
+}
+
+/// The VTP domain of a switch and its role in it
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Vtp {
+    pub domain: String,
+    #[serde(default)]
+    pub mode: VtpMode,
+    pub password: Option<String>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VtpMode {
+    #[default]
+    Server,
+    Client,
+    /// Don't apply the VLANs of the domain, but forward its advertisements
+    Transparent,
+}
+
+/// The spanning-tree settings of a switch
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpanningTree {
+    pub mode: Option<StpMode>,
+    /// The bridge priority of the switch in each VLAN
+    pub priorities: BTreeMap<u16, u32>,
+    /// The VLANs this switch should be the root bridge of
+    pub root_primary_for: Vec<u16>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StpMode {
+    Pvst,
+    RapidPvst,
+}
+
+/// The VLANs in which a switch drops the DHCP offers not coming from `server`
+///
+/// The ports leading to `server` are trusted
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DhcpSnooping {
+    pub vlans: Vec<u16>,
+    pub server: DefaultKey,
+}
+
+/// The VLANs carried by a trunk link
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Trunk {
+    /// The VLANs allowed on the trunk, or all of them if empty
+    pub allowed_vlans: Vec<u16>,
+    /// The VLAN of the untagged frames, if not VLAN 1
+    pub native_vlan: Option<u16>,
+}
+
+/// The multicast routing settings of a device
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Multicast {
+    /// The address of the static rendezvous point
+    pub rp_address: Option<IpAddr>,
+}
+
+/// A named sequence of match/set clauses, used for policy-based routing
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct RouteMap {
+    pub name: String,
+    pub entries: Vec<RouteMapEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct RouteMapEntry {
+    pub seq: u32,
+    #[serde(default)]
+    pub action: Action,
+    /// The `match` clauses, without the `match` keyword
+    #[serde(default, rename = "match")]
+    pub matches: Vec<String>,
+    /// The `set` clauses, without the `set` keyword
+    #[serde(default, rename = "set")]
+    pub sets: Vec<String>,
+}
+
+/// A class of traffic, for quality of service
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct ClassMap {
+    pub name: String,
+    /// Whether a packet belongs to the class when matching any clause, rather than all
+    #[serde(default)]
+    pub match_any: bool,
+    /// The `match` clauses, without the `match` keyword
+    #[serde(default, rename = "match")]
+    pub matches: Vec<String>,
+}
+
+/// How an interface treats each class of traffic
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct PolicyMap {
+    pub name: String,
+    pub classes: Vec<PolicyClass>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct PolicyClass {
+    /// The name of the class-map, or `class-default`
+    pub class: String,
+    /// The bandwidth guaranteed in kbit/s
+    pub bandwidth: Option<u32>,
+    /// The bandwidth of the low latency queue in kbit/s
+    pub priority: Option<u32>,
+    /// The rate the traffic is limited to in bit/s
+    pub police: Option<u32>,
+    pub set_dscp: Option<String>,
+}
+
+/// An access list, which is extended if any of its rules needs it
+///
+/// Lists with a numeric name are numbered ones, the others are named
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Acl {
+    pub name: String,
+    pub rules: Vec<AclRule>,
+}
+
+/// A rule of an access list, where a missing address matches any
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct AclRule {
+    pub action: Action,
+    pub protocol: Option<AclProtocol>,
+    pub src: Option<IpNet>,
+    pub dst: Option<IpNet>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+}
+
+impl AclRule {
+    /// Whether the rule matches more than the source address
+    fn is_extended(&self) -> bool {
+        self.protocol.is_some()
+            || self.dst.is_some()
+            || self.src_port.is_some()
+            || self.dst_port.is_some()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AclProtocol {
+    Ip,
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+/// Whether matching traffic or routes are allowed
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    #[default]
+    Permit,
+    Deny,
+}
+
+impl Action {
+    fn keyword(self) -> &'static str {
+        match self {
+            Action::Permit => "permit",
+            Action::Deny => "deny",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(try_from = "u8")]
+pub enum RipVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl TryFrom<u8> for RipVersion {
+    type Error = String;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            1 => Ok(RipVersion::V1),
+            2 => Ok(RipVersion::V2),
+            v => Err(format!("Invalid RIP version: {v}")),
+        }
+    }
+}
+
+/// The administrative distances overriding the default ones of each protocol
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Distances {
+    pub rip: Option<u8>,
+    pub ospf: Option<u8>,
+    pub eigrp: Option<u8>,
+}
+
+/// The weights of the components of the EIGRP composite metric
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct EigrpKValues {
+    pub k1: u8,
+    pub k2: u8,
+    pub k3: u8,
+    pub k4: u8,
+    pub k5: u8,
+}
+
+impl Default for EigrpKValues {
+    fn default() -> Self {
+        EigrpKValues {
+            k1: 1,
+            k2: 0,
+            k3: 1,
+            k4: 0,
+            k5: 0,
+        }
+    }
+}
+
+/// The routes each routing process imports from the others
+///
+/// In the YAML, each redistribution is either a boolean or a map
+/// of the options of [`Redistribution`]
+#[derive(Default, Debug, PartialEq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Redistributions {
+    /// Emit `redistribute rip subnets` under OSPF
+    #[serde(deserialize_with = "deserialize_redistribution")]
+    pub ospf_to_rip: Option<Redistribution>,
+    /// Emit `redistribute ospf 1` under RIP
+    #[serde(deserialize_with = "deserialize_redistribution")]
+    pub rip_to_ospf: Option<Redistribution>,
+    /// Emit `redistribute connected` under RIP
+    #[serde(deserialize_with = "deserialize_redistribution")]
+    pub rip_connected: Option<Redistribution>,
+    /// Emit `redistribute static` under RIP
+    #[serde(deserialize_with = "deserialize_redistribution")]
+    pub rip_static: Option<Redistribution>,
+    /// Emit `redistribute connected subnets` under OSPF
+    #[serde(deserialize_with = "deserialize_redistribution")]
+    pub ospf_connected: Option<Redistribution>,
+    /// Emit `redistribute static subnets` under OSPF
+    #[serde(deserialize_with = "deserialize_redistribution")]
+    pub ospf_static: Option<Redistribution>,
+}
+
+/// The options of a `redistribute` command
+#[derive(Default, Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(default)]
+pub struct Redistribution {
+    /// The seed metric of the redistributed routes. RIP defaults to 1
+    pub metric: Option<u32>,
+    /// The OSPF external metric type (1 or 2). Ignored by RIP
+    pub metric_type: Option<u8>,
+    /// The route-map filtering the redistributed routes
+    pub route_map: Option<String>,
+}
+
+impl Redistribution {
+    /// Write the command redistributing the routes of `source`,
+    /// with the OSPF-only options if `into_ospf` is set
+    fn write(&self, res: &mut String, source: &str, into_ospf: bool) {
+        write!(res, "   redistribute {source}").unwrap();
+        if into_ospf {
+            res.push_str(" subnets");
+        }
+        if let Some(metric) = self.metric {
+            write!(res, " metric {metric}").unwrap();
+        }
+        if let (Some(metric_type), true) = (self.metric_type, into_ospf) {
+            write!(res, " metric-type {metric_type}").unwrap();
+        }
+        if let Some(route_map) = &self.route_map {
+            write!(res, " route-map {route_map}").unwrap();
+        }
+        res.push('\n');
+    }
+}
+
+fn deserialize_redistribution<'de, D>(deserializer: D) -> Result<Option<Redistribution>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Enabled(bool),
+        Options(Redistribution),
+    }
+
+    Ok(match Repr::deserialize(deserializer)? {
+        Repr::Enabled(true) => Some(Redistribution::default()),
+        Repr::Enabled(false) => None,
+        Repr::Options(redistribution) => Some(redistribution),
+    })
+}
+
+pub struct DeviceBuilder<'a> {
+    app: &'a mut App,
+    device: Device,
+}
+
+impl DeviceBuilder<'_> {
+    pub fn name(mut self, name: String) -> Self {
+        self.device.name = name;
+        self
+    }
+
+    pub fn default_gateway(mut self, gateway: Option<IpAddr>) -> Self {
+        self.device.default_gateway = gateway;
+        self
+    }
+
+    pub fn dns_server(mut self, dns_server: Option<IpAddr>) -> Self {
+        self.device.dns_server = dns_server;
+        self
+    }
+
+    pub fn services(mut self, services: Services) -> Self {
+        self.device.services = services;
+        self
+    }
+
+    pub fn kind(mut self, kind: DeviceKind) -> Self {
+        self.device.kind = kind;
+        self
+    }
+
+    pub fn platform(mut self, platform: Option<Platform>) -> Self {
+        self.device.platform = platform;
+        self
+    }
+
+    pub fn ntp_server(mut self, ntp_server: Option<IpAddr>) -> Self {
+        self.device.ntp_server = ntp_server;
+        self
+    }
+
+    pub fn role(mut self, role: Option<Role>) -> Self {
+        self.device.role = role;
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.device.tags = tags;
+        self
+    }
+
+    pub fn numbering(mut self, numbering: Option<Numbering>) -> Self {
+        if let Some(numbering) = &numbering {
+            assert!(
+                numbering.pattern.contains("{n}"),
+                "The numbering pattern `{}` doesn't contain `{{n}}`",
+                numbering.pattern
+            );
+        }
+        self.device.numbering = numbering;
+        self
+    }
+
+    pub fn position(mut self, x: f32, y: f32) -> Self {
+        self.device.x = x;
+        self.device.y = y;
+        self
+    }
+
+    pub fn redistribute_ospf_to_rip(mut self, b: bool) -> Self {
+        self.device.redistributions.ospf_to_rip = b.then(Redistribution::default);
+        self
+    }
+
+    pub fn redistribute_rip_to_ospf(mut self, b: bool) -> Self {
+        self.device.redistributions.rip_to_ospf = b.then(Redistribution::default);
+        self
+    }
+
+    pub fn redistributions(mut self, redistributions: Redistributions) -> Self {
+        self.device.redistributions = redistributions;
+        self
+    }
+
+    pub fn rip_version(mut self, version: RipVersion) -> Self {
+        self.device.rip.version = version;
+        self
+    }
+
+    pub fn banner(mut self, banner: Option<String>) -> Self {
+        self.device.banner = banner;
+        self
+    }
+
+    pub fn passwords(mut self, passwords: Passwords) -> Self {
+        self.device.passwords = passwords;
+        self
+    }
+
+    pub fn user(mut self, user: LocalUser) -> Self {
+        self.device.users.push(user);
+        self
+    }
+
+    pub fn ssh(mut self, ssh: Option<Ssh>) -> Self {
+        self.device.ssh = ssh;
+        self
+    }
+
+    pub fn cdp(mut self, cdp: Option<bool>) -> Self {
+        self.device.cdp = cdp;
+        self
+    }
+
+    pub fn lldp(mut self, lldp: Option<bool>) -> Self {
+        self.device.lldp = lldp;
+        self
+    }
+
+    pub fn aaa(mut self, aaa: Option<Aaa>) -> Self {
+        self.device.aaa = aaa;
+        self
+    }
+
+    pub fn snmp(mut self, snmp: Option<Snmp>) -> Self {
+        self.device.snmp = snmp;
+        self
+    }
+
+    pub fn logging(mut self, logging: Option<Logging>) -> Self {
+        self.device.logging = logging;
+        self
+    }
+
+    pub fn dns(mut self, dns: DnsSettings) -> Self {
+        self.device.dns = dns;
+        self
+    }
+
+    pub fn rip_auto_summary(mut self, b: bool) -> Self {
+        self.device.rip.auto_summary = b;
+        self
+    }
+
+    pub fn key_chain(mut self, key_chain: KeyChain) -> Self {
+        self.device.key_chains.push(key_chain);
+        self
+    }
+
+    pub fn distances(mut self, distances: Distances) -> Self {
+        self.device.distances = distances;
+        self
+    }
+
+    pub fn vrf(mut self, vrf: Vrf) -> Self {
+        self.device.vrfs.push(vrf);
+        self
+    }
+
+    pub fn add_loopback(mut self, ip: &str) -> Self {
+        let ip = IpNet::from_str(ip).unwrap_or_else(|_| panic!("Failed to parse ip: {ip}"));
+        self.device.loopbacks.push(ip);
+        self
+    }
+
+    pub fn vlan(mut self, vlan: Vlan) -> Self {
+        self.device.vlans.push(vlan);
+        self
+    }
+
+    pub fn svi(mut self, svi: Svi) -> Self {
+        self.device.svis.push(svi);
+        self
+    }
+
+    pub fn spanning_tree(mut self, spanning_tree: SpanningTree) -> Self {
+        self.device.spanning_tree = spanning_tree;
+        self
+    }
+
+    pub fn dhcp_snooping(mut self, dhcp_snooping: Option<DhcpSnooping>) -> Self {
+        self.device.dhcp_snooping = dhcp_snooping;
+        self
+    }
+
+    pub fn vtp(mut self, vtp: Option<Vtp>) -> Self {
+        self.device.vtp = vtp;
+        self
+    }
+
+    pub fn multicast(mut self, multicast: Option<Multicast>) -> Self {
+        self.device.multicast = multicast;
+        self
+    }
+
+    pub fn route_map(mut self, route_map: RouteMap) -> Self {
+        self.device.route_maps.push(route_map);
+        self
+    }
+
+    pub fn prefix_list(mut self, prefix_list: PrefixList) -> Self {
+        self.device.prefix_lists.push(prefix_list);
+        self
+    }
+
+    pub fn acl(mut self, acl: Acl) -> Self {
+        self.device.acls.push(acl);
+        self
+    }
+
+    pub fn class_map(mut self, class_map: ClassMap) -> Self {
+        self.device.class_maps.push(class_map);
+        self
+    }
+
+    pub fn policy_map(mut self, policy_map: PolicyMap) -> Self {
+        self.device.policy_maps.push(policy_map);
+        self
+    }
+
+    pub fn distribute_list(mut self, distribute_list: DistributeList) -> Self {
+        self.device.distribute_lists.push(distribute_list);
+        self
+    }
+
+    pub fn eigrp_as(mut self, eigrp_as: Option<u16>) -> Self {
+        self.device.eigrp_as = eigrp_as;
+        self
+    }
+
+    pub fn eigrp_variance(mut self, variance: Option<u8>) -> Self {
+        self.device.eigrp_variance = variance;
+        self
+    }
+
+    pub fn eigrp_k_values(mut self, k_values: Option<EigrpKValues>) -> Self {
+        self.device.eigrp_k_values = k_values;
+        self
+    }
+
+    pub fn bgp_as(mut self, bgp_as: Option<u32>) -> Self {
+        self.device.bgp_as = bgp_as;
+        self
+    }
+
+    pub fn isis_net(mut self, net: Option<String>) -> Self {
+        self.device.isis_net = net;
+        self
+    }
+
+    /// Add a static route towards `prefix`, going through `next_hop`
+    pub fn static_route(self, prefix: &str, next_hop: &str) -> Self {
+        self.floating_static_route(prefix, next_hop, None)
+    }
+
+    /// Add a static route with the given administrative distance
+    pub fn floating_static_route(
+        mut self,
+        prefix: &str,
+        next_hop: &str,
+        distance: Option<u8>,
+    ) -> Self {
+        let prefix = IpNet::from_str(prefix)
+            .unwrap_or_else(|_| panic!("Failed to parse prefix: {prefix}"))
+            .trunc();
+        let next_hop = IpAddr::from_str(next_hop)
+            .unwrap_or_else(|_| panic!("Failed to parse next hop: {next_hop}"));
+
+        self.device.static_routes.push(StaticRoute {
+            prefix,
+            next_hop,
+            distance,
+        });
+        self
+    }
+
+    pub fn default_route(mut self, default_route: Option<DefaultRoute>) -> Self {
+        self.device.default_route = default_route;
+        self
+    }
+
+    pub fn originate_default_route(mut self, b: bool) -> Self {
+        self.device.originate_default_route = b;
+        self
+    }
+
+    pub fn ospf_router_id(mut self, router_id: Option<Ipv4Addr>) -> Self {
+        self.device.ospf_router_id = router_id;
+        self
+    }
+
+    pub fn ospf_mutual_redistribution(mut self, b: bool) -> Self {
+        self.device.ospf_mutual_redistribution = b;
+        self
+    }
+
+    pub fn finish(self) -> DefaultKey {
+        self.app.devices.insert(self.device)
+    }
+}
+
+pub struct LinkBuilder<'a> {
+    app: &'a mut App,
+    key: LinkKey,
+    /// The addresses of the two sides, or `None` if the link is unnumbered
+    addresses: Option<(IpNet, IpNet)>,
+    /// The addresses chosen for the two sides, instead of the first ones of the subnet
+    chosen: (Option<IpAddr>, Option<IpAddr>),
+}
+
+impl<'a> LinkBuilder<'a> {
+    fn link(&mut self) -> &mut Link {
+        &mut self.app.links[self.key]
+    }
+
+    /// Give the two sides the first two addresses of `ip`, unless it's the
+    /// address of a side or they are chosen with `address`. Without a subnet,
+    /// the link is unnumbered
+    pub fn subnet(mut self, ip: &str) -> Self {
+        self.addresses = Some(link_addresses(ip));
+        self
+    }
+
+    /// Give the side at `device` the address `ip` of the subnet
+    pub fn address(mut self, device: DefaultKey, ip: IpAddr) -> Self {
+        let (r1, r2) = self.link().ends;
+        if device == r1 {
+            self.chosen.0 = Some(ip);
+        } else if device == r2 {
+            self.chosen.1 = Some(ip);
+        } else {
+            panic!("The device isn't an end of the link");
+        }
+        self
+    }
+
+    pub fn ospf_area(mut self, area: u16) -> Self {
+        self.link().ospf_area = Some(area);
+        self
+    }
+
+    pub fn cost(mut self, cost: u16) -> Self {
+        self.link().ospf_cost = Some(cost);
+        self
+    }
+
+    pub fn ospf_auth(mut self, b: bool) -> Self {
+        self.link().ospf_auth = b;
+        self
+    }
+
+    pub fn ospf_network_type(mut self, network_type: OspfNetworkType) -> Self {
+        self.link().ospf_network_type = Some(network_type);
+        self
+    }
+
+    pub fn ospf_process(mut self, process: u16) -> Self {
+        self.link().ospf_process = Some(process);
+        self
+    }
+
+    pub fn eigrp(mut self, b: bool) -> Self {
+        self.link().eigrp = b;
+        self
+    }
+
+    pub fn bgp(mut self, b: bool) -> Self {
+        self.link().bgp = b;
+        self
+    }
+
+    pub fn isis(mut self, level: IsisLevel) -> Self {
+        self.link().isis = Some(level);
+        self
+    }
+
+    pub fn pim(mut self, b: bool) -> Self {
+        self.link().pim = b;
+        self
+    }
+
+    pub fn fhrp(mut self, group: FhrpGroup) -> Self {
+        self.link().fhrp = Some(group);
+        self
+    }
+
+    pub fn bfd(mut self, bfd: Bfd) -> Self {
+        self.link().bfd = Some(bfd);
+        self
+    }
+
+    pub fn interface_type(mut self, interface_type: InterfaceType) -> Self {
+        if let InterfaceType::Serial { dce, .. } = interface_type {
+            let (r1, r2) = self.link().ends;
+            assert!(
+                dce == r1 || dce == r2,
+                "The DCE side of a serial link must be one of its routers"
+            );
+        }
+        self.link().interface_type = interface_type;
+        self
+    }
+
+    pub fn ppp(mut self, ppp: Ppp) -> Self {
+        self.link().ppp = Some(ppp);
+        self
+    }
+
+    pub fn mtu(mut self, mtu: u16) -> Self {
+        self.link().mtu = Some(mtu);
+        self
+    }
+
+    pub fn bandwidth(mut self, kbps: u32) -> Self {
+        self.link().bandwidth = Some(kbps);
+        self
+    }
+
+    pub fn delay(mut self, delay: u32) -> Self {
+        self.link().delay = Some(delay);
+        self
+    }
+
+    pub fn eui64(mut self, b: bool) -> Self {
+        self.link().eui64 = b;
+        self
+    }
+
+    pub fn trunk(mut self, trunk: Trunk) -> Self {
+        self.link().trunk = Some(trunk);
+        self
+    }
+
+    pub fn access_vlan(mut self, vlan: u16) -> Self {
+        self.link().access_vlan = Some(vlan);
+        self
+    }
+
+    pub fn voice_vlan(mut self, vlan: u16) -> Self {
+        self.link().voice_vlan = Some(vlan);
+        self
+    }
+
+    pub fn finish(self) -> &'a mut Link {
+        if let Some(addresses) = self.addresses {
+            self.app.reserve(addresses.0);
+        }
+        let link = &mut self.app.links[self.key];
+        match self.addresses {
+            Some(addresses) => {
+                (link.r1, link.r2) = choose_addresses(addresses, self.chosen);
+            }
+            // Both interfaces borrow the address of their router's first loopback
+            None => {
+                assert_eq!(
+                    self.chosen,
+                    (None, None),
+                    "Addresses can only be chosen for the links with a subnet"
+                );
+                link.r1_options.unnumbered = Some(0);
+                link.r2_options.unnumbered = Some(0);
+            }
+        }
+        link
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_remove_ip() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+
+        app.link(r1, r2, "10.0.0.0/30", None);
+        assert_eq!(
+            app.get_directed_link(r1, r2).unwrap().close_ip,
+            "10.0.0.1/30".parse().unwrap(),
+        );
+        assert_eq!(
+            app.get_directed_link(r1, r2).unwrap().far_ip,
+            "10.0.0.2/30".parse().unwrap(),
+        );
+        assert_eq!(app.links.len(), 1);
+
+        app.unlink(r1, r2);
+        assert_eq!(app.links.len(), 0);
+        assert_eq!(app.get_directed_link(r1, r2), None);
+    }
+
+    #[test]
+    fn modify_link() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+
+        app.link(r1, r2, "10.0.0.0/30", None);
+        assert_eq!(
+            app.get_directed_link(r1, r2).unwrap().close_ip,
+            "10.0.0.1/30".parse().unwrap(),
+        );
+        assert_eq!(
+            app.get_directed_link(r1, r2).unwrap().far_ip,
+            "10.0.0.2/30".parse().unwrap(),
+        );
+
+        app.link(r2, r1, "10.0.0.4/30", None);
+        assert_eq!(
+            app.get_directed_link(r1, r2).unwrap().close_ip,
+            "10.0.0.5/30".parse().unwrap(),
+        );
+        assert_eq!(
+            app.get_directed_link(r1, r2).unwrap().far_ip,
             "10.0.0.6/30".parse().unwrap(),
         );
     }
+
+    #[test]
+    fn eigrp_networks() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").eigrp_as(Some(10)).finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").eigrp_as(Some(10)).finish();
+
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r1, r3, "10.0.0.4/30", None).eigrp = true;
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("router eigrp 10\n   network 10.0.0.4 0.0.0.3\nexit\n"));
+        assert!(!commands["R1"].contains("network 10.0.0.0 0.0.0.3"));
+        assert!(!commands["R2"].contains("router eigrp"));
+    }
+
+    #[test]
+    fn ebgp_neighbors() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").bgp_as(Some(65001)).finish();
+        let r2 = app.add_device("R2").bgp_as(Some(65002)).finish();
+        let r3 = app.add_device("R3").finish();
+
+        app.link(r1, r2, "10.0.0.0/30", None).bgp = true;
+        app.link(r1, r3, "10.0.0.4/30", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "router bgp 65001\n",
+            "   neighbor 10.0.0.2 remote-as 65002\n",
+            "   network 10.0.0.0 mask 255.255.255.252\n",
+            "exit\n",
+        )));
+        assert!(commands["R2"].contains("   neighbor 10.0.0.1 remote-as 65001\n"));
+        assert!(!commands["R3"].contains("router bgp"));
+    }
+
+    #[test]
+    fn ibgp_full_mesh() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").bgp_as(Some(65001)).finish();
+        let r2 = app.add_device("R2").bgp_as(Some(65001)).finish();
+        let r3 = app.add_device("R3").bgp_as(Some(65001)).finish();
+
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r2, r3, "10.0.0.4/30", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "router bgp 65001\n",
+            "   neighbor 10.0.0.2 remote-as 65001\n",
+            "   neighbor 10.0.0.6 remote-as 65001\n",
+            "   neighbor 10.0.0.6 update-source GigabitEthernet 0/0\n",
+            "exit\n",
+        )));
+        assert!(commands["R3"].contains(concat!(
+            "   neighbor 10.0.0.1 remote-as 65001\n",
+            "   neighbor 10.0.0.1 update-source GigabitEthernet 0/0\n",
+            "   neighbor 10.0.0.5 remote-as 65001\n",
+        )));
+    }
+
+    #[test]
+    fn static_routes() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .static_route("192.168.1.0/24", "10.0.0.2")
+            .static_route("172.16.5.7/16", "10.0.0.2")
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "ip route 192.168.1.0 255.255.255.0 10.0.0.2\n",
+            "ip route 172.16.0.0 255.255.0.0 10.0.0.2\n",
+        )));
+    }
+
+    #[test]
+    fn default_route() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").originate_default_route(true).finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.get_device("R1").unwrap().default_route = Some(DefaultRoute::ExitInterface(r3));
+        app.get_device("R2").unwrap().default_route =
+            Some(DefaultRoute::NextHop("10.0.0.1".parse().unwrap()));
+
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r1, r3, "10.0.0.4/30", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("ip route 0.0.0.0 0.0.0.0 GigabitEthernet 1/0\n"));
+        assert!(commands["R1"].contains("   default-information originate\n"));
+        assert!(commands["R2"].contains("ip route 0.0.0.0 0.0.0.0 10.0.0.1\n"));
+        assert!(!commands["R2"].contains("default-information originate"));
+    }
+
+    #[test]
+    fn ospf_area_types() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+
+        app.set_area_type(1, AreaType::TotallyStubby);
+        app.set_area_type(2, AreaType::Nssa);
+        app.link(r1, r2, "10.0.0.0/30", Some(0));
+        app.link(r2, r3, "10.0.0.4/30", Some(1));
+
+        let commands = app.to_commands();
+        assert!(!commands["R1"].contains("   area"));
+        assert!(commands["R2"].contains("   area 1 stub no-summary\n"));
+        assert!(commands["R3"].contains("   area 1 stub\n"));
+        assert!(!commands["R3"].contains("nssa"));
+    }
+
+    #[test]
+    fn ospf_authentication() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+
+        app.set_ospf_md5_key("s3cret");
+        app.authenticate_area(0);
+        app.link(r1, r2, "10.0.0.0/30", Some(0));
+        app.link(r2, r3, "10.0.0.4/30", Some(1)).ospf_auth = true;
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   area 0 authentication message-digest\n"));
+        assert!(commands["R1"].contains("   ip ospf message-digest-key 1 md5 s3cret\n"));
+        assert!(!commands["R1"].contains("ip ospf authentication"));
+        assert!(commands["R3"].contains(concat!(
+            "   ip ospf authentication message-digest\n",
+            "   ip ospf message-digest-key 1 md5 s3cret\n",
+        )));
+        assert!(!commands["R3"].contains("area 1 authentication"));
+    }
+
+    #[test]
+    fn ospfv3() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .ospf_router_id(Some(Ipv4Addr::new(1, 1, 1, 1)))
+            .finish();
+        let r2 = app.add_device("R2").finish();
+
+        app.link(r1, r2, "2001:db8::/64", Some(0));
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   ipv6 ospf 1 area 0\n"));
+        assert!(commands["R1"].contains("ipv6 router ospf 1\n   router-id 1.1.1.1\nexit\n"));
+        assert!(commands["R1"].contains("router ospf 1\n   router-id 1.1.1.1\nexit\n"));
+    }
+
+    #[test]
+    fn ripng() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.rip_enabled.extend([r1, r2]);
+
+        app.link(r1, r2, "2001:db8::/64", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   ipv6 rip RIPNG enable\n"));
+        assert!(commands["R1"].contains("ipv6 router rip RIPNG\nexit\n"));
+        assert!(commands["R1"].contains("router rip\n   version 2\nexit\n"));
+    }
+
+    #[test]
+    fn rip_settings() {
+        let mut app = App::new();
+
+        app.add_device("R1").finish();
+        app.add_device("R2")
+            .rip_version(RipVersion::V1)
+            .rip_auto_summary(false)
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("router rip\n   version 2\nexit\n"));
+        assert!(commands["R2"].contains("router rip\n   version 1\n   no auto-summary\nexit\n"));
+    }
+
+    #[test]
+    fn redistribution_options() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .redistributions(Redistributions {
+                ospf_to_rip: Some(Redistribution {
+                    metric: Some(20),
+                    metric_type: Some(1),
+                    route_map: None,
+                }),
+                rip_to_ospf: Some(Redistribution {
+                    route_map: Some("FROM_OSPF".to_string()),
+                    ..Default::default()
+                }),
+                ospf_connected: Some(Redistribution::default()),
+                ..Default::default()
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   redistribute rip subnets metric 20 metric-type 1\n"));
+        assert!(commands["R1"].contains("   redistribute ospf 1 metric 1 route-map FROM_OSPF\n"));
+        assert!(commands["R1"].contains("   redistribute connected subnets\n"));
+    }
+
+    #[test]
+    fn isis() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .isis_net(Some("49.0001.0000.0000.0001.00".to_string()))
+            .finish();
+        let r2 = app.add_device("R2").finish();
+
+        app.link(r1, r2, "10.0.0.0/30", None).isis = Some(IsisLevel::Level2);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   ip router isis\n   isis circuit-type level-2-only\n"));
+        assert!(commands["R1"].contains("router isis\n   net 49.0001.0000.0000.0001.00\nexit\n"));
+        assert!(!commands["R2"].contains("isis"));
+    }
+
+    #[test]
+    fn bgp_route_reflector() {
+        let mut app = App::new();
+
+        let rr = app.add_device("RR").bgp_as(Some(65001)).finish();
+        let c1 = app.add_device("C1").bgp_as(Some(65001)).finish();
+        let c2 = app.add_device("C2").bgp_as(Some(65001)).finish();
+
+        app.link(rr, c1, "10.0.0.0/30", None);
+        app.link(rr, c2, "10.0.0.4/30", None);
+        app.link(c1, c2, "10.0.0.8/30", None);
+        assert_eq!(app.validate_route_reflectors(), Ok(()));
+
+        app.get_device("RR").unwrap().bgp_rr_clients = vec![c1];
+        assert!(app.validate_route_reflectors().is_err());
+        app.get_device("RR").unwrap().bgp_rr_clients = vec![c1, c2];
+        assert_eq!(app.validate_route_reflectors(), Ok(()));
+
+        let commands = app.to_commands();
+        assert!(commands["RR"].contains("   neighbor 10.0.0.2 route-reflector-client\n"));
+        assert!(commands["RR"].contains("   neighbor 10.0.0.6 route-reflector-client\n"));
+        assert!(!commands["C1"].contains("neighbor 10.0.0.10"));
+        assert!(commands["C1"].contains("   neighbor 10.0.0.1 remote-as 65001\n"));
+    }
+
+    #[test]
+    fn bgp_policies() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").bgp_as(Some(65001)).finish();
+        let r2 = app.add_device("R2").bgp_as(Some(65002)).finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+
+        app.get_device("R1").unwrap().bgp_policies.push(BgpPolicy {
+            neighbor: r2,
+            direction: Direction::In,
+            local_preference: Some(200),
+            med: None,
+            community: Some("65001:100".to_string()),
+        });
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "route-map BGP_R2_IN permit 10\n",
+            "   set local-preference 200\n",
+            "   set community 65001:100\n",
+            "exit\n",
+        )));
+        assert!(commands["R1"].contains(concat!(
+            "   neighbor 10.0.0.2 route-map BGP_R2_IN in\n",
+            "   neighbor 10.0.0.2 send-community\n",
+        )));
+    }
+
+    #[test]
+    fn summaries() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").eigrp_as(Some(1)).finish();
+        let r2 = app.add_device("R2").eigrp_as(Some(1)).finish();
+        let r3 = app.add_device("R3").eigrp_as(Some(1)).finish();
+        let r4 = app.add_device("R4").eigrp_as(Some(1)).finish();
+
+        app.link(r1, r2, "192.168.0.0/30", Some(1)).eigrp = true;
+        app.link(r1, r3, "192.168.0.4/30", Some(1)).eigrp = true;
+        app.link(r1, r4, "10.0.0.0/30", Some(0)).eigrp = true;
+
+        app.get_device("R1").unwrap().summaries = vec![
+            Summary {
+                protocol: SummaryProtocol::Ospf { area: 1 },
+                prefix: None,
+            },
+            Summary {
+                protocol: SummaryProtocol::Eigrp { towards: r4 },
+                prefix: None,
+            },
+        ];
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   area 1 range 192.168.0.0 255.255.255.248\n"));
+        assert!(commands["R1"].contains(concat!(
+            "interface GigabitEthernet 2/0\n",
+            "   description Link to R4 (GigabitEthernet 0/0)\n",
+            "   ip address 10.0.0.1 255.255.255.252\n",
+            "   ip summary-address eigrp 1 192.168.0.0 255.255.255.248\n",
+        )));
+    }
+
+    #[test]
+    fn floating_static_routes() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .static_route("192.168.1.0/24", "10.0.0.2")
+            .floating_static_route("192.168.1.0/24", "10.0.0.6", Some(250))
+            .finish();
+        assert_eq!(app.validate_floating_routes(), Ok(()));
+        assert!(
+            app.to_commands()["R1"].contains("ip route 192.168.1.0 255.255.255.0 10.0.0.6 250\n")
+        );
+
+        app.add_device("R2")
+            .floating_static_route("192.168.2.0/24", "10.0.0.6", Some(250))
+            .finish();
+        assert!(app.validate_floating_routes().is_err());
+    }
+
+    #[test]
+    fn policy_based_routing() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .route_map(RouteMap {
+                name: "PBR".to_string(),
+                entries: vec![RouteMapEntry {
+                    seq: 10,
+                    action: Action::Permit,
+                    matches: vec!["ip address 101".to_string()],
+                    sets: vec!["ip next-hop 10.0.0.2".to_string()],
+                }],
+            })
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.interface_mut(r1, r2).unwrap().policy_route_map = Some("PBR".to_string());
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "route-map PBR permit 10\n",
+            "   match ip address 101\n",
+            "   set ip next-hop 10.0.0.2\n",
+            "exit\n",
+        )));
+        assert!(commands["R1"].contains("   ip policy route-map PBR\n"));
+    }
+
+    #[test]
+    fn distribute_lists() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .prefix_list(PrefixList {
+                name: "NO_LANS".to_string(),
+                entries: vec![PrefixListEntry {
+                    seq: 5,
+                    action: Action::Deny,
+                    prefix: "192.168.0.0/16".parse().unwrap(),
+                    ge: None,
+                    le: Some(24),
+                }],
+            })
+            .distribute_list(DistributeList {
+                protocol: FilteredProtocol::Ospf,
+                direction: Direction::In,
+                filter: RouteFilter::PrefixList("NO_LANS".to_string()),
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("ip prefix-list NO_LANS seq 5 deny 192.168.0.0/16 le 24\n"));
+        assert!(commands["R1"].contains("router ospf 1\n   distribute-list prefix NO_LANS in\n"));
+        assert!(!commands["R1"].contains("router rip\n   version 2\n   distribute-list"));
+    }
+
+    #[test]
+    fn vrf_lite() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .vrf(Vrf {
+                name: "RED".to_string(),
+                rd: Some("65000:1".to_string()),
+            })
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+
+        app.link(r1, r2, "10.0.0.0/30", Some(0));
+        app.link(r1, r3, "10.0.0.4/30", Some(0));
+        app.interface_mut(r1, r3).unwrap().vrf = Some("RED".to_string());
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("vrf definition RED\n   rd 65000:1\n"));
+        assert!(commands["R1"].contains(concat!(
+            "interface GigabitEthernet 1/0\n",
+            "   description Link to R3 (GigabitEthernet 0/0)\n",
+            "   vrf forwarding RED\n",
+            "   ip address 10.0.0.5 255.255.255.252\n",
+        )));
+        assert!(commands["R1"].contains(concat!(
+            "router ospf 1\n",
+            "   network 10.0.0.0 0.0.0.3 area 0\n",
+            "exit\n\n",
+            "router ospf 2 vrf RED\n",
+            "   network 10.0.0.4 0.0.0.3 area 0\n",
+            "exit\n",
+        )));
+    }
+
+    #[test]
+    fn hsrp() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let isp = app.add_device("ISP").finish();
+
+        app.link(r1, r2, "192.168.1.0/29", None).fhrp = Some(FhrpGroup {
+            protocol: FhrpProtocol::Hsrp,
+            group: 1,
+            virtual_ip: "192.168.1.6".parse().unwrap(),
+            load_balancing: None,
+        });
+        app.link(r1, isp, "10.0.0.0/30", None);
+
+        let r1_side = app.interface_mut(r1, r2).unwrap();
+        r1_side.fhrp_priority = Some(110);
+        r1_side.fhrp_preempt = true;
+        r1_side.fhrp_track = Some(FhrpTrack {
+            towards: "ISP".to_string(),
+            decrement: 20,
+        });
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "   standby 1 ip 192.168.1.6\n",
+            "   standby 1 priority 110\n",
+            "   standby 1 preempt\n",
+            "   standby 1 track GigabitEthernet 1/0 20\n",
+        )));
+        assert!(commands["R2"].contains("   standby 1 ip 192.168.1.6\n   no shutdown\n"));
+    }
+
+    #[test]
+    fn vrrp() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let isp = app.add_device("ISP").finish();
+
+        app.link(r1, r2, "192.168.1.0/29", None).fhrp = Some(FhrpGroup {
+            protocol: FhrpProtocol::Vrrp,
+            group: 5,
+            virtual_ip: "192.168.1.6".parse().unwrap(),
+            load_balancing: None,
+        });
+        app.link(r1, isp, "10.0.0.0/30", None);
+        app.interface_mut(r1, r2).unwrap().fhrp_track = Some(FhrpTrack {
+            towards: "ISP".to_string(),
+            decrement: 20,
+        });
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("track 5 interface GigabitEthernet 1/0 line-protocol\n"));
+        assert!(
+            commands["R1"].contains("   vrrp 5 ip 192.168.1.6\n   vrrp 5 track 5 decrement 20\n")
+        );
+        assert!(!commands["R2"].contains("track"));
+    }
+
+    #[test]
+    fn glbp() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+
+        app.link(r1, r2, "192.168.1.0/29", None).fhrp = Some(FhrpGroup {
+            protocol: FhrpProtocol::Glbp,
+            group: 1,
+            virtual_ip: "192.168.1.6".parse().unwrap(),
+            load_balancing: Some(GlbpLoadBalancing::Weighted),
+        });
+        app.interface_mut(r1, r2).unwrap().glbp_weighting = Some(150);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(concat!(
+            "   glbp 1 ip 192.168.1.6\n",
+            "   glbp 1 load-balancing weighted\n",
+            "   glbp 1 weighting 150\n",
+        )));
+    }
+
+    #[test]
+    fn ospf_network_type() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", Some(0)).ospf_network_type =
+            Some(OspfNetworkType::PointToPoint);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   ip ospf network point-to-point\n"));
+        assert!(commands["R2"].contains("   ip ospf network point-to-point\n"));
+    }
+
+    #[test]
+    fn ospf_processes() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .ospf_mutual_redistribution(true)
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link(r1, r2, "10.0.0.0/30", Some(0));
+        app.link(r1, r3, "10.0.1.0/30", Some(1)).ospf_process = Some(2);
+
+        let commands = app.to_commands();
+        let r1_commands = &commands["R1"];
+        assert!(r1_commands.contains(
+            "router ospf 1\n   redistribute ospf 2 subnets\n   network 10.0.0.0 0.0.0.3 area 0\nexit\n"
+        ));
+        assert!(r1_commands.contains(
+            "router ospf 2\n   redistribute ospf 1 subnets\n   network 10.0.1.0 0.0.0.3 area 1\nexit\n"
+        ));
+        assert!(commands["R3"].contains("router ospf 2\n"));
+    }
+
+    #[test]
+    fn eigrp_unequal_cost() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .eigrp_as(Some(10))
+            .eigrp_variance(Some(2))
+            .finish();
+        let r2 = app.add_device("R2").eigrp_as(Some(10)).finish();
+        app.link(r1, r2, "10.0.0.0/30", None).eigrp = true;
+        app.interface_mut(r1, r2).unwrap().delay = Some(200);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   delay 200\n"));
+        assert!(commands["R1"].contains("router eigrp 10\n   variance 2\n"));
+        assert!(!commands["R2"].contains("delay"));
+    }
+
+    #[test]
+    fn switch_ports() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        app.link(r1, s1, "10.0.0.0/24", Some(0));
+
+        let commands = app.to_commands();
+        assert!(commands["S1"].contains(
+            "interface FastEthernet 0/1\n   description Link to R1 (GigabitEthernet 0/0)\n   switchport mode access\n   no shutdown\nexit\n"
+        ));
+        assert!(!commands["S1"].contains("ip address"));
+        assert!(!commands["S1"].contains("router ospf"));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0\n   description Link to S1 (FastEthernet 0/1)\n   ip address 10.0.0.1"
+        ));
+    }
+
+    #[test]
+    fn vlan_database() {
+        let mut app = App::new();
+
+        app.add_device("S1")
+            .kind(DeviceKind::Switch)
+            .vlan(Vlan {
+                id: 10,
+                name: Some("Sales".into()),
+            })
+            .vlan(Vlan { id: 20, name: None })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["S1"].contains("vlan 10\n   name Sales\nexit\n\nvlan 20\nexit\n"));
+    }
+
+    #[test]
+    fn trunk_ports() {
+        let mut app = App::new();
+
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let s2 = app
+            .add_device("S2")
+            .kind(DeviceKind::MultilayerSwitch)
+            .finish();
+        app.link(s1, s2, "10.0.0.0/30", None).trunk = Some(Trunk {
+            allowed_vlans: vec![10, 20],
+            native_vlan: Some(99),
+        });
+
+        let commands = app.to_commands();
+        let trunk = "   switchport mode trunk\n   switchport trunk native vlan 99\n   switchport trunk allowed vlan 10,20\n";
+        assert!(commands["S1"].contains(&format!(
+            "interface FastEthernet 0/1\n   description Link to S2 (FastEthernet 0/1)\n{trunk}"
+        )));
+        assert!(
+            commands["S2"].contains(&format!("   switchport trunk encapsulation dot1q\n{trunk}"))
+        );
+        assert!(!commands["S2"].contains("no switchport"));
+    }
+
+    #[test]
+    fn access_ports() {
+        let mut app = App::new();
+
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let pc = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        let link = app.link(s1, pc, "192.168.10.0/24", None);
+        link.access_vlan = Some(10);
+        link.voice_vlan = Some(150);
+
+        let commands = app.to_commands();
+        assert!(commands["S1"].contains(
+            "interface FastEthernet 0/1\n   description Link to PC1 (FastEthernet0)\n   switchport mode access\n   switchport access vlan 10\n   switchport voice vlan 150\n"
+        ));
+        assert!(matches!(commands["PC1"], Config::HostSettings(_)));
+    }
+
+    #[test]
+    fn vtp_password_mismatch() {
+        let mut app = App::new();
+
+        let vtp = |password: &str| Vtp {
+            domain: "LAB".into(),
+            mode: VtpMode::Client,
+            password: Some(password.into()),
+        };
+        app.add_device("S1")
+            .kind(DeviceKind::Switch)
+            .vtp(Some(vtp("cisco")))
+            .finish();
+        app.add_device("S2")
+            .kind(DeviceKind::Switch)
+            .vtp(Some(vtp("cisco")))
+            .finish();
+        assert!(app.validate_vtp_passwords().is_ok());
+        assert!(app.to_commands()["S1"]
+            .contains("vtp domain LAB\nvtp mode client\nvtp password cisco\n"));
+
+        app.add_device("S3")
+            .kind(DeviceKind::Switch)
+            .vtp(Some(vtp("class")))
+            .finish();
+        assert!(app.validate_vtp_passwords().is_err());
+    }
+
+    #[test]
+    fn spanning_tree() {
+        let mut app = App::new();
+
+        app.add_device("S1")
+            .kind(DeviceKind::Switch)
+            .spanning_tree(SpanningTree {
+                mode: Some(StpMode::RapidPvst),
+                priorities: BTreeMap::from([(20, 8192)]),
+                root_primary_for: vec![10],
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["S1"].contains(
+            "spanning-tree mode rapid-pvst\nspanning-tree vlan 20 priority 8192\nspanning-tree vlan 10 root primary\n"
+        ));
+    }
+
+    #[test]
+    fn etherchannel() {
+        let mut app = App::new();
+
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let s2 = app.add_device("S2").kind(DeviceKind::Switch).finish();
+        app.link(s1, s2, "10.0.0.0/30", None).trunk = Some(Trunk::default());
+        app.bundle(s1, s2, 1, 2);
+
+        let commands = app.to_commands();
+        for name in ["S1", "S2"] {
+            let far = if name == "S1" { "S2" } else { "S1" };
+            assert!(commands[name].contains(&format!(
+                "interface FastEthernet 0/1\n   description Link to {far} (FastEthernet 0/1)\n\
+                \x20  channel-group 1 mode active\n   no shutdown\nexit\n\n\
+                interface FastEthernet 0/2\n   description Link to {far} (FastEthernet 0/2)\n\
+                \x20  channel-group 1 mode active\n   no shutdown\nexit\n\n\
+                interface Port-channel 1\n   switchport mode trunk\nexit\n"
+            )));
+        }
+    }
+
+    #[test]
+    fn port_security() {
+        let mut app = App::new();
+
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let pc = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        app.link(s1, pc, "192.168.10.0/24", None);
+        app.interface_mut(s1, pc).unwrap().port_security = Some(PortSecurity {
+            maximum: Some(2),
+            violation: Some(PortSecurityViolation::Restrict),
+            sticky: true,
+        });
+
+        let commands = app.to_commands();
+        let expected = [
+            "   switchport mode access",
+            "   switchport port-security",
+            "   switchport port-security maximum 2",
+            "   switchport port-security violation restrict",
+            "   switchport port-security mac-address sticky\n",
+        ];
+        assert!(commands["S1"].contains(&expected.join("\n")));
+    }
+
+    #[test]
+    fn router_on_a_stick() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        app.link(r1, s1, "10.0.0.0/30", None).trunk = Some(Trunk::default());
+        app.subinterface(r1, s1, 10, "192.168.10.0/24");
+        app.subinterface(r1, s1, 20, "192.168.20.0/24");
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0.10\n   encapsulation dot1Q 10\n   ip address 192.168.10.1 255.255.255.0\nexit\n\n\
+            interface GigabitEthernet 0/0.20\n   encapsulation dot1Q 20\n   ip address 192.168.20.1 255.255.255.0\nexit\n"
+        ));
+        assert!(!commands["S1"].contains("encapsulation"));
+    }
+
+    #[test]
+    fn multilayer_switch() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let s1 = app
+            .add_device("S1")
+            .kind(DeviceKind::MultilayerSwitch)
+            .svi(Svi {
+                vlan: 10,
+                ip: "192.168.10.1/24".parse().unwrap(),
+            })
+            .finish();
+        let pc = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        app.link(r1, s1, "10.0.0.0/30", Some(0));
+        app.interface_mut(s1, r1).unwrap().routed = true;
+        app.link(s1, pc, "192.168.10.0/24", None).access_vlan = Some(10);
+
+        let commands = app.to_commands();
+        let s1_commands = &commands["S1"];
+        assert!(
+            s1_commands.starts_with("enable\nconfigure terminal\n\nhostname S1\nip routing\n\n")
+        );
+        assert!(s1_commands.contains(
+            "interface FastEthernet 0/1\n   description Link to R1 (GigabitEthernet 0/0)\n   no switchport\n   ip address 10.0.0.2 255.255.255.252\n"
+        ));
+        assert!(s1_commands.contains("interface FastEthernet 0/2\n   description Link to PC1 (FastEthernet0)\n   switchport mode access\n"));
+        assert!(s1_commands.contains(
+            "interface Vlan10\n   ip address 192.168.10.1 255.255.255.0\n   no shutdown\nexit\n"
+        ));
+        assert!(s1_commands.contains("router ospf 1\n"));
+    }
+
+    #[test]
+    fn dhcp_snooping_trust() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let s2 = app.add_device("S2").kind(DeviceKind::Switch).finish();
+        let pc = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        app.link(r1, s1, "10.0.0.0/24", None);
+        app.link(s1, s2, "10.0.1.0/24", None).trunk = Some(Trunk::default());
+        app.link(s2, pc, "10.0.2.0/24", None);
+        app.get_device("S2").unwrap().dhcp_snooping = Some(DhcpSnooping {
+            vlans: vec![1],
+            server: r1,
+        });
+
+        let commands = app.to_commands();
+        let s2_commands = &commands["S2"];
+        assert!(s2_commands.contains("ip dhcp snooping\nip dhcp snooping vlan 1\n"));
+        assert!(s2_commands.contains("   switchport mode trunk\n   ip dhcp snooping trust\n"));
+        assert_eq!(s2_commands.matches("snooping trust").count(), 1);
+    }
+
+    #[test]
+    fn intervlan_routing() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let s2 = app.add_device("S2").kind(DeviceKind::Switch).finish();
+        let pc1 = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        let pc2 = app.add_device("PC2").kind(DeviceKind::Pc).finish();
+        app.link(r1, s1, "10.0.0.0/30", None);
+        app.link(s1, s2, "10.0.1.0/30", None);
+        app.link(s1, pc1, "192.168.10.0/24", None);
+        app.link(s2, pc2, "192.168.20.0/24", None);
+        app.setup_intervlan_routing(r1, &[(10, "192.168.10.0/24"), (20, "192.168.20.0/24")]);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("interface GigabitEthernet 0/0.10\n"));
+        assert!(commands["R1"].contains("interface GigabitEthernet 0/0.20\n"));
+        for switch in ["S1", "S2"] {
+            assert!(commands[switch].contains("vlan 10\nexit\n\nvlan 20\nexit\n"));
+            assert!(commands[switch].contains("   switchport mode trunk\n"));
+        }
+        assert!(commands["S1"].contains(
+            "interface FastEthernet 0/3\n   description Link to PC1 (FastEthernet0)\n   switchport mode access\n   switchport access vlan 10\n"
+        ));
+        assert!(commands["S2"].contains(
+            "interface FastEthernet 0/2\n   description Link to PC2 (FastEthernet0)\n   switchport mode access\n   switchport access vlan 20\n"
+        ));
+    }
+
+    #[test]
+    fn host_settings() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let pc = app
+            .add_device("PC1")
+            .kind(DeviceKind::Pc)
+            .dns_server(Some("8.8.8.8".parse().unwrap()))
+            .finish();
+        let laptop = app.add_device("Laptop1").kind(DeviceKind::Laptop).finish();
+        app.link(r1, s1, "192.168.1.0/24", None);
+        app.link(s1, pc, "192.168.1.0/24", None);
+        app.link(s1, laptop, "10.0.0.0/24", None);
+
+        let commands = app.to_commands();
+        assert_eq!(
+            commands["PC1"],
+            Config::HostSettings(
+                "IPv4 Address: 192.168.1.2\nSubnet Mask: 255.255.255.0\nDefault Gateway: 192.168.1.1\nDNS Server: 8.8.8.8\n"
+                    .into()
+            )
+        );
+        assert!(matches!(commands["R1"], Config::Cli(_)));
+        assert!(!commands["Laptop1"].contains("Default Gateway"));
+    }
+
+    #[test]
+    fn server_services() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let server = app
+            .add_device("Server1")
+            .kind(DeviceKind::Server)
+            .services(Services {
+                http: true,
+                dns_records: vec![DnsRecord {
+                    name: "www.lab.local".into(),
+                    address: "192.168.1.2".parse().unwrap(),
+                }],
+                dhcp_pools: vec![DhcpPool {
+                    name: "LAN".into(),
+                    start: "192.168.1.100/24".parse().unwrap(),
+                    max_users: 50,
+                    default_gateway: Some(Ipv4Addr::new(192, 168, 1, 1)),
+                    dns_server: None,
+                }],
+                ..Default::default()
+            })
+            .finish();
+        app.link(r1, server, "192.168.1.0/24", None);
+
+        let commands = app.to_commands();
+        let expected = [
+            "Services",
+            "   HTTP: On",
+            "   DNS: On",
+            "      www.lab.local A 192.168.1.2",
+            "   DHCP: On",
+            "      Pool LAN",
+            "         Default Gateway: 192.168.1.1",
+            "         Start IP Address: 192.168.1.100",
+            "         Subnet Mask: 255.255.255.0",
+            "         Maximum Number of Users: 50\n",
+        ];
+        assert!(commands["Server1"].contains(&expected.join("\n")));
+    }
+
+    #[test]
+    fn dhcp_relay() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let server = app.add_device("Server1").kind(DeviceKind::Server).finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r2, server, "10.0.1.0/24", None);
+        app.interface_mut(r1, r2).unwrap().dhcp_relay = Some(DhcpRelay::Device("Server1".into()));
+        app.interface_mut(r2, r1).unwrap().dhcp_relay =
+            Some(DhcpRelay::Address("10.0.1.200".parse().unwrap()));
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   ip helper-address 10.0.1.2\n"));
+        assert!(commands["R2"].contains("   ip helper-address 10.0.1.200\n"));
+    }
+
+    #[test]
+    fn dns_settings() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .dns(DnsSettings {
+                domain_name: Some("lab.local".into()),
+                name_servers: vec!["192.168.1.2".parse().unwrap()],
+                domain_lookup: Some(false),
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].starts_with(
+            "enable\nconfigure terminal\n\nhostname R1\n\nip domain-name lab.local\nip name-server 192.168.1.2\nno ip domain-lookup\n\n"
+        ));
+    }
+
+    #[test]
+    fn acls() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .acl(Acl {
+                name: "10".into(),
+                rules: vec![AclRule {
+                    src: Some("192.168.1.0/24".parse().unwrap()),
+                    ..Default::default()
+                }],
+            })
+            .acl(Acl {
+                name: "WEB".into(),
+                rules: vec![
+                    AclRule {
+                        protocol: Some(AclProtocol::Tcp),
+                        dst: Some("10.0.0.2/32".parse().unwrap()),
+                        dst_port: Some(80),
+                        ..Default::default()
+                    },
+                    AclRule {
+                        action: Action::Deny,
+                        ..Default::default()
+                    },
+                ],
+            })
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.interface_mut(r1, r2).unwrap().acl_out = Some("WEB".into());
+
+        let commands = app.to_commands();
+        let r1_commands = &commands["R1"];
+        assert!(r1_commands.contains("access-list 10 permit 192.168.1.0 0.0.0.255\n"));
+        assert!(r1_commands.contains(
+            "ip access-list extended WEB\n   permit tcp any host 10.0.0.2 eq 80\n   deny ip any any\nexit\n"
+        ));
+        assert!(r1_commands.contains("   ip access-group WEB out\n"));
+    }
+
+    #[test]
+    fn syslog() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .logging(Some(Logging {
+                server: "Syslog".into(),
+                level: Some(SyslogLevel::Warnings),
+                timestamps: true,
+            }))
+            .finish();
+        let server = app.add_device("Syslog").kind(DeviceKind::Server).finish();
+        app.link(r1, server, "10.0.0.0/24", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "service timestamps log datetime msec\nlogging host 10.0.0.2\nlogging trap warnings\n"
+        ));
+    }
+
+    #[test]
+    fn snmp() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .snmp(Some(Snmp {
+                location: Some("Lab".into()),
+                communities: vec![SnmpCommunity {
+                    name: "public".into(),
+                    access: SnmpAccess::Ro,
+                }],
+                users: vec![SnmpUser {
+                    name: "admin".into(),
+                    group: "ADMINS".into(),
+                    auth_password: "authpass".into(),
+                    priv_password: Some("privpass".into()),
+                }],
+                trap_hosts: vec![SnmpTrapHost {
+                    server: "NMS".into(),
+                    community: "public".into(),
+                }],
+                ..Default::default()
+            }))
+            .finish();
+        let nms = app.add_device("NMS").kind(DeviceKind::Server).finish();
+        app.link(r1, nms, "10.0.0.0/24", None);
+
+        let commands = app.to_commands();
+        let expected = [
+            "snmp-server location Lab",
+            "snmp-server community public RO",
+            "snmp-server group ADMINS v3 priv",
+            "snmp-server user admin ADMINS v3 auth sha authpass priv aes 128 privpass",
+            "snmp-server enable traps",
+            "snmp-server host 10.0.0.2 version 2c public\n",
+        ];
+        assert!(commands["R1"].contains(&expected.join("\n")));
+    }
+
+    #[test]
+    fn local_users_and_aaa() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .user(LocalUser {
+                name: "admin".into(),
+                privilege: Some(15),
+                secret: "cisco".into(),
+            })
+            .aaa(Some(Aaa {
+                login: Some(AaaMethodList {
+                    name: None,
+                    methods: vec![AaaMethod::Local, AaaMethod::Enable],
+                }),
+                exec: Some(AaaMethodList {
+                    name: Some("SHELL".into()),
+                    methods: vec![AaaMethod::Local],
+                }),
+            }))
+            .finish();
+
+        let commands = app.to_commands();
+        let expected = [
+            "username admin privilege 15 secret cisco",
+            "aaa new-model",
+            "aaa authentication login default local enable",
+            "aaa authorization exec SHELL local\n",
+        ];
+        assert!(commands["R1"].contains(&expected.join("\n")));
+    }
+
+    #[test]
+    fn ssh() {
+        let mut app = App::new();
+
+        app.add_device("R1")
+            .ssh(Some(Ssh {
+                domain_name: Some("lab.local".into()),
+                ..Default::default()
+            }))
+            .finish();
+
+        let commands = app.to_commands();
+        let expected = [
+            "ip domain-name lab.local",
+            "crypto key generate rsa general-keys modulus 1024",
+            "ip ssh version 2",
+            "line vty 0 4",
+            "   transport input ssh",
+            "   login local",
+            "exit\n",
+        ];
+        assert!(commands["R1"].contains(&expected.join("\n")));
+    }
+
+    #[test]
+    fn default_passwords() {
+        let mut app = App::new();
+
+        app.set_default_passwords(Passwords {
+            enable_secret: Some("class".into()),
+            console: Some("cisco".into()),
+            vty: None,
+        });
+        app.add_device("R1")
+            .passwords(Passwords {
+                enable_secret: Some("secret".into()),
+                ..Default::default()
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].starts_with(
+            "enable\nconfigure terminal\n\nhostname R1\n\nenable secret secret\nline console 0\n   password cisco\n   login\nexit\n\n"
+        ));
+    }
+
+    #[test]
+    fn banner() {
+        let mut app = App::new();
+
+        app.set_default_banner("Authorized access to {name} only");
+        app.add_device("R1").finish();
+        app.add_device("R2")
+            .banner(Some("Lab #1 ^_^".into()))
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("banner motd ^Authorized access to R1 only^\n"));
+        assert!(commands["R2"].contains("banner motd %Lab #1 ^_^%\n"));
+    }
+
+    #[test]
+    fn hostname_sanitization() {
+        let mut app = App::new();
+
+        app.add_device("Core router_1 ").finish();
+
+        let commands = app.to_commands();
+        assert!(commands["Core router_1 "]
+            .starts_with("enable\nconfigure terminal\n\nhostname Core-router-1\n\n"));
+    }
+
+    #[test]
+    fn loopbacks() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .eigrp_as(Some(1))
+            .add_loopback("1.1.1.1/32")
+            .finish();
+        let r2 = app.add_device("R2").eigrp_as(Some(1)).finish();
+        let link = app.link(r1, r2, "10.0.0.0/30", Some(0));
+        link.eigrp = true;
+
+        let commands = app.to_commands();
+        let r1_commands = &commands["R1"];
+        assert!(r1_commands
+            .contains("interface Loopback0\n   ip address 1.1.1.1 255.255.255.255\nexit\n"));
+        assert!(r1_commands.contains("   network 1.1.1.1 0.0.0.0 area 0\n"));
+        assert!(r1_commands.contains("   network 1.1.1.1 0.0.0.0\n"));
+        assert!(!commands["R2"].contains("Loopback"));
+    }
+
+    #[test]
+    fn site_to_site_vpn() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let isp = app.add_device("ISP").finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, isp, "200.0.0.0/30", None);
+        app.link(isp, r2, "200.0.0.4/30", None);
+        app.add_vpn(Vpn {
+            pre_shared_key: "vpnkey".into(),
+            peers: [
+                VpnPeer {
+                    router: r1,
+                    outside: isp,
+                    networks: vec!["192.168.1.0/24".parse().unwrap()],
+                },
+                VpnPeer {
+                    router: r2,
+                    outside: isp,
+                    networks: vec!["192.168.2.0/24".parse().unwrap()],
+                },
+            ],
+        });
+
+        let commands = app.to_commands();
+        let expected = [
+            "crypto isakmp key vpnkey address 200.0.0.6",
+            "ip access-list extended VPN-R2",
+            "   permit ip 192.168.1.0 0.0.0.255 192.168.2.0 0.0.0.255",
+            "exit",
+            "crypto map VPN-MAP 10 ipsec-isakmp",
+            "   set peer 200.0.0.6",
+            "   set transform-set VPN-SET",
+            "   match address VPN-R2\n",
+        ];
+        assert!(commands["R1"].contains(&expected.join("\n")));
+        assert!(commands["R1"].contains("   crypto map VPN-MAP\n"));
+        assert!(commands["R2"].contains("crypto isakmp key vpnkey address 200.0.0.1\n"));
+        assert!(!commands["ISP"].contains("crypto"));
+    }
+
+    #[test]
+    fn qos_policies() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .class_map(ClassMap {
+                name: "VOICE".into(),
+                match_any: false,
+                matches: vec!["dscp ef".into()],
+            })
+            .policy_map(PolicyMap {
+                name: "WAN".into(),
+                classes: vec![
+                    PolicyClass {
+                        class: "VOICE".into(),
+                        bandwidth: None,
+                        priority: Some(512),
+                        police: None,
+                        set_dscp: None,
+                    },
+                    PolicyClass {
+                        class: "class-default".into(),
+                        bandwidth: None,
+                        priority: None,
+                        police: Some(1000000),
+                        set_dscp: Some("default".into()),
+                    },
+                ],
+            })
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.interface_mut(r1, r2).unwrap().service_policy_out = Some("WAN".into());
+
+        let commands = app.to_commands();
+        let expected = [
+            "class-map match-all VOICE",
+            "   match dscp ef",
+            "exit",
+            "policy-map WAN",
+            "   class VOICE",
+            "      priority 512",
+            "   exit",
+            "   class class-default",
+            "      police 1000000",
+            "      set dscp default",
+            "   exit",
+            "exit\n",
+        ];
+        assert!(commands["R1"].contains(&expected.join("\n")));
+        assert!(commands["R1"].contains("   service-policy output WAN\n"));
+    }
+
+    #[test]
+    fn interface_descriptions() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.interface_mut(r1, r2).unwrap().description = Some("Uplink to core".into());
+
+        let commands = app.to_commands();
+        assert!(commands["R1"]
+            .contains("interface GigabitEthernet 0/0\n   description Uplink to core\n"));
+        assert!(commands["R2"].contains(
+            "interface GigabitEthernet 0/0\n   description Link to R1 (GigabitEthernet 0/0)\n"
+        ));
+    }
+
+    #[test]
+    fn serial_links() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link(r1, r3, "10.0.1.0/30", None);
+        app.add_link(r1, r2)
+            .subnet("10.0.0.0/30")
+            .interface_type(InterfaceType::Serial {
+                dce: r2,
+                clock_rate: 64000,
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface Serial 1/0\n   description Link to R2 (Serial 0/0)\n   ip address 10.0.0.1 255.255.255.252\n   no shutdown\n"
+        ));
+        assert!(commands["R2"].contains(
+            "interface Serial 0/0\n   description Link to R1 (Serial 1/0)\n   ip address 10.0.0.2 255.255.255.252\n   clock rate 64000\n"
+        ));
+        assert!(commands["R1"].contains("interface GigabitEthernet 0/0\n"));
+    }
+
+    #[test]
+    fn frame_relay() {
+        let mut app = App::new();
+
+        let fr = app
+            .add_device("FR")
+            .kind(DeviceKind::FrameRelayCloud)
+            .finish();
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.frame_relay(fr, r1, r2, "10.0.12.0/30", None, [102, 201]);
+        app.frame_relay(fr, r1, r3, "10.0.13.0/30", None, [103, 301]);
+
+        let commands = app.to_commands();
+        assert_eq!(
+            *commands["FR"],
+            [
+                "Serial0\n",
+                "   DLCI 102: R1-R2\n",
+                "   DLCI 103: R1-R3\n",
+                "Serial1\n",
+                "   DLCI 201: R2-R1\n",
+                "Serial2\n",
+                "   DLCI 301: R3-R1\n",
+                "\n",
+                "Frame Relay\n",
+                "   Serial0 R1-R2 <-> Serial1 R2-R1\n",
+                "   Serial0 R1-R3 <-> Serial2 R3-R1\n",
+            ]
+            .concat()
+        );
+        assert!(commands["R1"].contains(
+            "interface Serial 0/0\n   description Link to FR (Serial0)\n   encapsulation frame-relay\n   no shutdown\nexit\n\n\
+            interface Serial 0/0.102 point-to-point\n   description Link to R2 (Serial 0/0)\n\
+            \x20  ip address 10.0.12.1 255.255.255.252\n   frame-relay interface-dlci 102\nexit\n\n\
+            interface Serial 0/0.103 point-to-point\n"
+        ));
+        assert!(commands["R3"].contains(
+            "interface Serial 0/0\n   description Link to FR (Serial2)\n   encapsulation frame-relay\n\
+            \x20  ip address 10.0.13.2 255.255.255.252\n   frame-relay map ip 10.0.13.1 301 broadcast\n"
+        ));
+    }
+
+    #[test]
+    fn ppp_authentication() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_link(r1, r2)
+            .subnet("10.0.0.0/30")
+            .interface_type(InterfaceType::Serial {
+                dce: r1,
+                clock_rate: 64000,
+            })
+            .ppp(Ppp {
+                authentication: Some(PppAuthentication::Chap),
+                password: "cisco".into(),
+            })
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("username R2 password cisco\n"));
+        assert!(commands["R2"].contains("username R1 password cisco\n"));
+        assert!(commands["R1"]
+            .contains("   clock rate 64000\n   encapsulation ppp\n   ppp authentication chap\n"));
+
+        app.link(r1, r2, "10.0.0.0/30", None).ppp = Some(Ppp {
+            authentication: Some(PppAuthentication::Pap),
+            password: "cisco".into(),
+        });
+        let commands = app.to_commands();
+        assert!(commands["R2"].contains(
+            "   encapsulation ppp\n   ppp authentication pap\n   ppp pap sent-username R2 password cisco\n"
+        ));
+    }
+
+    #[test]
+    fn link_mtu() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", Some(0)).mtu = Some(1400);
+
+        let commands = app.to_commands();
+        for name in ["R1", "R2"] {
+            assert!(commands[name].contains("   mtu 1400\n   ip mtu 1400\n"));
+        }
+    }
+
+    #[test]
+    fn unnumbered_links() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").add_loopback("1.1.1.1/32").finish();
+        let r2 = app.add_device("R2").add_loopback("2.2.2.2/32").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link_unnumbered(r1, r2, Some(0));
+        app.link(r1, r3, "10.0.0.0/30", Some(0));
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0\n   description Link to R2 (GigabitEthernet 0/0)\n   ip unnumbered Loopback0\n"
+        ));
+        assert!(commands["R1"].contains(
+            "router ospf 1\n   network 10.0.0.0 0.0.0.3 area 0\n   network 1.1.1.1 0.0.0.0 area 0\n"
+        ));
+        assert!(commands["R2"].contains("router ospf 1\n   network 2.2.2.2 0.0.0.0 area 0\n"));
+        assert!(app
+            .validate()
+            .iter()
+            .all(|issue| issue.code != IssueCode::UnnumberedWithoutLoopback));
+
+        // R3 has no loopback to borrow the address of
+        app.link_unnumbered(r2, r3, Some(0));
+        app.interface_mut(r2, r3).unwrap().unnumbered = Some(1);
+        let issues: Vec<_> = app
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.code == IssueCode::UnnumberedWithoutLoopback)
+            .map(|issue| (issue.severity, issue.message))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (
+                    Severity::Error,
+                    "The interface of `R2` towards `R3` is unnumbered, but there is no Loopback1 to borrow the address of".to_string()
+                ),
+                (
+                    Severity::Error,
+                    "The interface of `R3` towards `R2` is unnumbered, but there is no Loopback0 to borrow the address of".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn discovery_protocols() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .cdp(Some(false))
+            .lldp(Some(true))
+            .finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        app.link(r1, s1, "10.0.0.0/30", None);
+        let options = app.interface_mut(s1, r1).unwrap();
+        options.cdp = Some(false);
+        options.lldp = Some(false);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("no cdp run\nlldp run\n"));
+        assert!(!commands["S1"].contains("cdp run"));
+        assert!(commands["S1"].contains(
+            "   switchport mode access\n   no cdp enable\n   no lldp transmit\n   no lldp receive\n"
+        ));
+    }
+
+    #[test]
+    fn ipv6_addresses() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link(r1, r2, "2001:db8::/64", None);
+        app.link(r2, r3, "10.0.0.0/30", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("hostname R1\nipv6 unicast-routing\n"));
+        assert!(commands["R1"].contains("   ipv6 address 2001:db8::1/64\n   ipv6 enable\n"));
+        assert!(!commands["R1"].contains("ip address"));
+        assert!(commands["R2"].contains("   ip address 10.0.0.1 255.255.255.252\n"));
+        assert!(!commands["R3"].contains("ipv6"));
+    }
+
+    #[test]
+    fn dual_stack_links() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "10.0.0.0/30", Some(0));
+        app.dual_stack(r1, r2, "2001:db8::/64");
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "   ip address 10.0.0.1 255.255.255.252\n   ipv6 address 2001:db8::1/64\n   ipv6 enable\n"
+        ));
+        assert!(commands["R2"].contains("hostname R2\nipv6 unicast-routing\n"));
+        assert!(commands["R2"].contains("   ipv6 ospf 1 area 0\n"));
+        assert!(commands["R2"].contains("router ospf 1\n   network 10.0.0.0 0.0.0.3 area 0\n"));
+        assert!(commands["R2"].contains("ipv6 router ospf 1\n"));
+    }
+
+    #[test]
+    fn eui64_addresses() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.link(r1, r2, "2001:db8:0:1::/64", None).eui64 = true;
+
+        let commands = app.to_commands();
+        for name in ["R1", "R2"] {
+            assert!(commands[name].contains("   ipv6 address 2001:db8:0:1::/64 eui-64\n"));
+        }
+    }
+
+    #[test]
+    fn ipv6_autoconfig() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let pc1 = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        app.link(r1, pc1, "2001:db8:1::/64", None);
+        app.interface_mut(r1, pc1).unwrap().ipv6_autoconfig = Some(Ipv6Autoconfig {
+            mode: Ipv6AddressingMode::Stateful,
+            dns_server: Some("2001:db8::53".parse().unwrap()),
+            domain_name: None,
+        });
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "ipv6 dhcp pool DHCPV6-0\n   address prefix 2001:db8:1::/64\n   dns-server 2001:db8::53\nexit\n"
+        ));
+        assert!(commands["R1"].contains(
+            "   ipv6 nd managed-config-flag\n   ipv6 nd prefix default no-autoconfig\n   ipv6 dhcp server DHCPV6-0\n"
+        ));
+    }
+
+    #[test]
+    fn address_pool() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.set_address_pool("10.0.0.0/24", 30);
+        app.link(r1, r2, "10.0.0.0/30", None);
+        let subnet = app.allocate_subnet(r2, r3);
+        app.link(r2, r3, &subnet.to_string(), None);
+        let subnet = app.allocate_subnet(r1, r3);
+        app.link(r1, r3, &subnet.to_string(), None);
+
+        assert_eq!(
+            app.allocation_table(),
+            "R2 - R3: 10.0.0.4/30\nR1 - R3: 10.0.0.8/30\n"
+        );
+        let commands = app.to_commands();
+        assert!(commands["R3"].contains("   ip address 10.0.0.6 255.255.255.252\n"));
+    }
+
+    #[test]
+    fn vlsm_planning() {
+        let mut app = App::new();
+
+        let subnets = app.plan_vlsm(
+            "192.168.0.0/24",
+            &[("Office", 20), ("Lab", 100), ("Link", 2), ("Guests", 50)],
+        );
+        let subnets: Vec<String> = subnets.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            subnets,
+            [
+                "192.168.0.192/27",
+                "192.168.0.0/25",
+                "192.168.0.224/30",
+                "192.168.0.128/26",
+            ]
+        );
+        assert!(app
+            .allocation_table()
+            .starts_with("Office (20 hosts): 192.168.0.192/27\n"));
+    }
+
+    #[test]
+    fn overlapping_addresses() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        let pc1 = app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        app.link(r1, s1, "192.168.0.0/24", None);
+        app.link(s1, pc1, "192.168.0.0/24", None);
+        app.link(r1, r2, "10.0.0.0/30", None);
+        assert_eq!(app.validate_addresses(), Ok(()));
+
+        app.link(r2, r3, "10.0.0.0/30", None);
+        assert_eq!(
+            app.validate_addresses(),
+            Err("`R1` (GigabitEthernet 1/0) and `R2` (GigabitEthernet 1/0) have the same address 10.0.0.1".into())
+        );
+
+        app.unlink(r2, r3);
+        app.get_device("R3")
+            .unwrap()
+            .loopbacks
+            .push("10.0.0.3/32".parse().unwrap());
+        assert_eq!(
+            app.validate_addresses(),
+            Err("`R1` (GigabitEthernet 1/0) and `R3` (Loopback0) have the overlapping subnets 10.0.0.0/30 and 10.0.0.3/32".into())
+        );
+    }
+
+    #[test]
+    fn topology_validation() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_device("R3").finish();
+        app.add_device("R4").add_loopback("4.4.4.4/32").finish();
+        app.link(r1, r2, "10.0.0.0/30", Some(1));
+        app.rip_enabled.push(r1);
+
+        let codes: Vec<_> = app
+            .validate()
+            .into_iter()
+            .map(|issue| (issue.code, issue.devices))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (IssueCode::RipWithoutNeighbors, vec!["R1".to_string()]),
+                (IssueCode::NoInterfaces, vec!["R3".to_string()]),
+                (IssueCode::IsolatedDevice, vec!["R4".to_string()]),
+                (
+                    IssueCode::MissingBackboneArea,
+                    vec!["R1".to_string(), "R2".to_string()]
+                ),
+            ]
+        );
+
+        app.link(r1, r2, "10.0.0.0/30", Some(0));
+        app.rip_enabled.push(r2);
+        assert!(app
+            .validate()
+            .iter()
+            .all(|issue| issue.severity == Severity::Warning
+                && issue.code != IssueCode::MissingBackboneArea));
+    }
+
+    #[test]
+    fn graph_connectivity() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        let r4 = app.add_device("R4").finish();
+        let r5 = app.add_device("R5").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r2, r3, "10.0.0.4/30", None);
+        app.link(r4, r5, "10.0.0.8/30", None);
+
+        assert!(!app.is_connected());
+        assert_eq!(app.partitions(), [vec![r1, r2, r3], vec![r4, r5]]);
+        assert_eq!(app.path_between(r1, r3), Some(vec![r1, r2, r3]));
+        assert_eq!(app.path_between(r1, r4), None);
+        assert!(app
+            .validate()
+            .iter()
+            .any(|issue| issue.code == IssueCode::Partitioned
+                && issue.message.ends_with("R1, R2, R3 | R4, R5")));
+
+        app.link(r3, r4, "10.0.0.12/30", None);
+        assert!(app.is_connected());
+        assert_eq!(app.path_between(r5, r1), Some(vec![r5, r4, r3, r2, r1]));
+        app.link(r1, r5, "10.0.0.16/30", None);
+        assert_eq!(app.path_between(r5, r2), Some(vec![r5, r1, r2]));
+    }
+
+    #[test]
+    fn auto_layout() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").position(10.0, 20.0).finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        let r4 = app.add_device("R4").finish();
+        let r5 = app.add_device("R5").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r1, r3, "10.0.0.4/30", None);
+        app.link(r2, r4, "10.0.0.8/30", None);
+
+        app.auto_layout(&[r2, r3, r4, r5]);
+        let position = |key| (app.devices[key].x, app.devices[key].y);
+        assert_eq!(position(r1), (10.0, 20.0));
+        assert_eq!(position(r2), (200.0, 0.0));
+        assert_eq!(position(r3), (200.0, 100.0));
+        assert_eq!(position(r4), (400.0, 50.0));
+        assert_eq!(position(r5), (0.0, 200.0));
+    }
+
+    #[test]
+    fn topology_templates() {
+        let mut app = App::new();
+        app.set_address_pool("10.0.0.0/24", 30);
+
+        let ring = app.template_ring("R", 3, Some(0));
+        assert_eq!(app.links.len(), 3);
+        assert!(app.is_connected());
+        let link = app.get_directed_link(ring[2], ring[0]).unwrap();
+        assert_eq!(link.close_ip, "10.0.0.10/30".parse().unwrap());
+
+        let mesh = app.template_full_mesh("M", 4, None);
+        assert_eq!(app.links.len(), 3 + 6);
+        assert_eq!(
+            app.path_between(mesh[1], mesh[3]),
+            Some(vec![mesh[1], mesh[3]])
+        );
+
+        let star = app.template_star("S", 4, None);
+        assert_eq!(
+            app.path_between(star[1], star[3]),
+            Some(vec![star[1], star[0], star[3]])
+        );
+
+        let hub = app.template_hub_and_spoke("H", 3, None);
+        assert_eq!(app.devices[hub[3]].kind, DeviceKind::FrameRelayCloud);
+        let commands = app.to_commands();
+        assert!(commands["H1"].contains("interface Serial 0/0.103 point-to-point\n"));
+        assert!(commands["H1"].contains("   frame-relay interface-dlci 103\n"));
+        assert!(commands["H3"].contains("   frame-relay map ip 10.0.0.53 101 broadcast\n"));
+        assert_eq!(app.partitions().len(), 4);
+        assert!(app.validate_addresses().is_ok());
+        assert_eq!(app.devices[hub[0]].name, "H1");
+    }
+
+    #[test]
+    fn random_topology() {
+        let topology = RandomTopology {
+            seed: 42,
+            devices: 10,
+            min_degree: 2,
+            max_degree: 4,
+            protocols: vec![(RoutingProtocol::Rip, 1), (RoutingProtocol::Eigrp, 1)],
+        };
+
+        let app = topology.generate();
+        assert_eq!(app.devices.len(), 10);
+        assert!(app.is_connected());
+        assert!(app.validate().is_empty());
+        for key in app.devices.keys() {
+            let degree = app.links_of(key).len();
+            assert!((1..=4).contains(&degree));
+        }
+
+        // The same seed gives the same topology
+        assert_eq!(app.to_commands(), topology.generate().to_commands());
+        let other = RandomTopology {
+            seed: 43,
+            ..topology
+        }
+        .generate();
+        assert_ne!(app.to_commands(), other.to_commands());
+    }
+
+    #[test]
+    fn lan_segments() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        let pc = app.add_device("PC").kind(DeviceKind::Pc).finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        app.segment(s1, &[r1, r2, r3, pc], "192.168.1.0/24", Some(0));
+        app.rip_enabled.extend([r1, r2]);
+
+        let commands = app.to_commands();
+        assert!(commands["R2"].contains(
+            "interface GigabitEthernet 0/0\n   \
+            description Link to S1 (FastEthernet 0/2)\n   \
+            ip address 192.168.1.2 255.255.255.0\n"
+        ));
+        for router in ["R1", "R2", "R3"] {
+            assert!(commands[router].contains("   network 192.168.1.0 0.0.0.255 area 0\n"));
+        }
+        // RIP runs towards the other RIP routers, across the switch
+        assert!(commands["R1"].contains("router rip\n   version 2\n   network 192.168.1.0\n"));
+        assert!(commands["PC"].contains("IPv4 Address: 192.168.1.4\n"));
+        assert!(commands["PC"].contains("Default Gateway: 192.168.1.1\n"));
+        assert!(app.validate_addresses().is_ok());
+    }
+
+    #[test]
+    fn link_builder() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_link(r2, r1)
+            .subnet("10.0.0.0/30")
+            .ospf_area(0)
+            .cost(10)
+            .mtu(1400)
+            .finish();
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0\n   \
+            description Link to R2 (GigabitEthernet 0/0)\n   \
+            ip address 10.0.0.1 255.255.255.252\n   \
+            mtu 1400\n   \
+            ip mtu 1400\n   \
+            ip ospf cost 10\n"
+        ));
+        assert!(commands["R2"].contains("   network 10.0.0.0 0.0.0.3 area 0\n"));
+    }
+
+    #[test]
+    fn remove_device() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r1, r3, "10.0.0.4/30", None);
+        app.rip_enabled.extend([r1, r2, r3]);
+
+        assert_eq!(app.remove_device(r2).name, "R2");
+        assert!(app.devices.get(r2).is_none());
+        assert_eq!(app.links.len(), 1);
+        assert_eq!(app.rip_enabled, [r1, r3]);
+        assert!(!app.to_commands().contains_key("R2"));
+
+        // The interface of the removed link isn't reused
+        let r4 = app.add_device("R4").finish();
+        app.link(r1, r4, "10.0.0.8/30", None);
+        let commands = app.to_commands();
+        assert!(!commands["R1"].contains("Link to R2"));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 1/0\n   description Link to R3 (GigabitEthernet 0/0)\n"
+        ));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 2/0\n   description Link to R4 (GigabitEthernet 0/0)\n"
+        ));
+    }
+
+    #[test]
+    fn rename_device() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .logging(Some(Logging {
+                server: "SRV".into(),
+                level: None,
+                timestamps: false,
+            }))
+            .finish();
+        let srv = app.add_device("SRV").kind(DeviceKind::Server).finish();
+        app.link(r1, srv, "10.0.0.0/30", None);
+        app.interface_mut(r1, srv).unwrap().dhcp_relay = Some(DhcpRelay::Device("SRV".into()));
+
+        assert!(app.rename_device(srv, "R1").is_err());
+        app.rename_device(srv, "Syslog").unwrap();
+        app.rename_device(r1, "Edge").unwrap();
+
+        let commands = app.to_commands();
+        assert!(commands.contains_key("Syslog") && commands.contains_key("Edge"));
+        assert!(commands["Edge"].contains("hostname Edge\n"));
+        assert!(commands["Edge"].contains("   description Link to Syslog (FastEthernet0)\n"));
+        assert!(commands["Edge"].contains("   ip helper-address 10.0.0.2\n"));
+        assert!(commands["Edge"].contains("logging host 10.0.0.2\n"));
+    }
+
+    #[test]
+    fn device_accessors() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+
+        assert_eq!(app.find_key_by_name("R2"), Some(r2));
+        assert_eq!(app.find_key_by_name("R3"), None);
+        assert_eq!(app.device(r1).unwrap().name, "R1");
+
+        app.device_mut(r1).unwrap().x = 100.0;
+        assert_eq!(app.device(r1).unwrap().x, 100.0);
+
+        app.remove_device(r1);
+        assert!(app.device(r1).is_none());
+        assert!(app.device_mut(r1).is_none());
+    }
+
+    #[test]
+    fn link_iteration() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link(r2, r1, "10.0.0.0/30", Some(0));
+        app.link(r3, r1, "10.0.0.4/30", None);
+
+        let mut links: Vec<_> = app
+            .links()
+            .map(|(a, b, link)| (a, b, link.addresses().0, link.ospf_area()))
+            .collect();
+        links.sort_by_key(|&(_, _, ip, _)| ip);
+        assert_eq!(
+            links,
+            [
+                (r1, r2, "10.0.0.1/30".parse().unwrap(), Some(0)),
+                (r1, r3, "10.0.0.5/30".parse().unwrap(), None),
+            ]
+        );
+
+        let links = app.links_of(r1);
+        let far: Vec<_> = links.iter().map(|link| link.far_key()).collect();
+        assert_eq!(far, [r2, r3]);
+        assert_eq!(links[1].close_ip(), "10.0.0.5/30".parse().unwrap());
+        assert_eq!(app.links_of(r3)[0].far_ip(), "10.0.0.5/30".parse().unwrap());
+
+        app.link_mut(r3, r1).unwrap().ospf_cost = Some(5);
+        assert!(app.to_commands()["R3"].contains("   ip ospf cost 5\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The DCE side of a serial link must be one of its routers")]
+    fn serial_dce_outside_link() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.add_link(r1, r2).interface_type(InterfaceType::Serial {
+            dce: r3,
+            clock_rate: 64000,
+        });
+    }
+
+    #[test]
+    fn parallel_links() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_link(r1, r2).subnet("10.0.0.0/30").finish();
+        app.add_link(r2, r1)
+            .subnet("10.0.0.4/30")
+            .cost(100)
+            .finish();
+
+        let keys = app.links_between(r2, r1).to_vec();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(app.link_by_key(keys[1]).unwrap().ospf_cost(), Some(100));
+        assert_eq!(app.links_of(r1).len(), 2);
+        // The pair-based methods refer to the last link
+        app.interface_mut(r1, r2).unwrap().description = Some("Backup".into());
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0\n   \
+            description Link to R2 (GigabitEthernet 0/0)\n   \
+            ip address 10.0.0.1 255.255.255.252\n"
+        ));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 1/0\n   \
+            description Backup\n   \
+            ip address 10.0.0.5 255.255.255.252\n"
+        ));
+        assert!(app.validate_addresses().is_ok());
+
+        // `link` updates the existing link instead
+        app.link(r1, r2, "10.0.0.8/30", None);
+        assert_eq!(app.links.len(), 2);
+
+        app.unlink(r1, r2);
+        assert!(app.links_between(r1, r2).is_empty());
+        assert!(app.links_of(r2).is_empty());
+    }
+
+    #[test]
+    fn port_types() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r1, s1, "192.168.0.1/24", None);
+        app.interface_mut(r1, r2).unwrap().port_type = Some(PortType::FastEthernet);
+        app.interface_mut(s1, r1).unwrap().port_type = Some(PortType::GigabitEthernet);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface FastEthernet 0/0\n   description Link to R2 (GigabitEthernet 0/0)\n"
+        ));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 1/0\n   description Link to S1 (GigabitEthernet 0/1)\n"
+        ));
+        assert!(commands["R2"].contains(
+            "interface GigabitEthernet 0/0\n   description Link to R1 (FastEthernet 0/0)\n"
+        ));
+        assert!(commands["S1"].contains("interface GigabitEthernet 0/1\n"));
+    }
+
+    #[test]
+    fn device_platforms() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .platform(Some(Platform::Isr4331))
+            .finish();
+        let r2 = app
+            .add_device("R2")
+            .platform(Some(Platform::Isr1941))
+            .finish();
+        let s1 = app
+            .add_device("S1")
+            .kind(DeviceKind::Switch)
+            .platform(Some(Platform::Catalyst2960))
+            .finish();
+        app.link(r1, s1, "192.168.0.1/24", None);
+        let serial = InterfaceType::Serial {
+            dce: r1,
+            clock_rate: 64000,
+        };
+        app.add_link(r1, r2)
+            .subnet("10.0.0.0/30")
+            .interface_type(serial)
+            .finish();
+        app.add_link(r1, r2).subnet("10.0.0.4/30").finish();
+        app.interface_mut(s1, r1).unwrap().port_type = Some(PortType::GigabitEthernet);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0/0\n   description Link to S1 (GigabitEthernet 0/1)\n"
+        ));
+        assert!(commands["R1"].contains("interface Serial 0/1/0\n"));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0/1\n   description Link to R2 (GigabitEthernet 0/0)\n"
+        ));
+        assert!(commands["R2"]
+            .contains("interface Serial 0/0/0\n   description Link to R1 (Serial 0/1/0)\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "R1 has more GigabitEthernet ports than a 1941")]
+    fn device_platform_inventory() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .platform(Some(Platform::Isr1941))
+            .finish();
+        for (name, subnet) in [
+            ("R2", "10.0.0.0/30"),
+            ("R3", "10.0.0.4/30"),
+            ("R4", "10.0.0.8/30"),
+        ] {
+            let r = app.add_device(name).finish();
+            app.link(r1, r, subnet, None);
+        }
+        app.to_commands();
+    }
+
+    #[test]
+    fn interface_numbering() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .numbering(Some(Numbering::new("0/{n}", 0)))
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        let s1 = app
+            .add_device("S1")
+            .kind(DeviceKind::Switch)
+            .numbering(Some(Numbering::new("1/0/{n}", 5)))
+            .finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r1, s1, "192.168.0.1/24", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/0\n   description Link to R2 (GigabitEthernet 0/0)\n"
+        ));
+        assert!(commands["R1"].contains(
+            "interface GigabitEthernet 0/1\n   description Link to S1 (FastEthernet 1/0/5)\n"
+        ));
+        assert!(commands["S1"].contains("interface FastEthernet 1/0/5\n"));
+    }
+
+    #[test]
+    fn point_to_point_subnets() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.link(r1, r2, "10.0.0.0/31", None);
+        app.link(r2, r3, "2001:db8::/127", None);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("ip address 10.0.0.0 255.255.255.254\n"));
+        assert!(commands["R2"].contains("ip address 10.0.0.1 255.255.255.254\n"));
+        assert!(commands["R2"].contains("ipv6 address 2001:db8::/127\n"));
+        assert!(commands["R3"].contains("ipv6 address 2001:db8::1/127\n"));
+    }
+
+    #[test]
+    fn chosen_addresses() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.add_link(r2, r1)
+            .subnet("10.0.0.0/24")
+            .address(r2, "10.0.0.1".parse().unwrap())
+            .finish();
+        app.add_link(r3, r1)
+            .subnet("10.0.1.0/24")
+            .address(r3, "10.0.1.254".parse().unwrap())
+            .address(r1, "10.0.1.100".parse().unwrap())
+            .finish();
+
+        let link = app.get_directed_link(r2, r1).unwrap();
+        assert_eq!(link.close_ip, "10.0.0.1/24".parse().unwrap());
+        assert_eq!(link.far_ip, "10.0.0.2/24".parse().unwrap());
+        let link = app.get_directed_link(r3, r1).unwrap();
+        assert_eq!(link.close_ip, "10.0.1.254/24".parse().unwrap());
+        assert_eq!(link.far_ip, "10.0.1.100/24".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "The address 10.0.1.1 is outside of 10.0.0.0/24")]
+    fn chosen_address_outside_subnet() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_link(r1, r2)
+            .subnet("10.0.0.0/24")
+            .address(r1, "10.0.1.1".parse().unwrap())
+            .finish();
+    }
+
+    #[test]
+    fn interface_shutdown() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let s1 = app.add_device("S1").kind(DeviceKind::Switch).finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.link(r2, s1, "192.168.0.1/24", None);
+        app.interface_mut(r1, r2).unwrap().shutdown = true;
+        app.interface_mut(s1, r2).unwrap().shutdown = true;
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   shutdown\nexit\n"));
+        assert!(!commands["R1"].contains("no shutdown"));
+        assert!(!commands["R2"].contains("   shutdown\n"));
+        assert!(commands["S1"].contains("   switchport mode access\n   shutdown\nexit\n"));
+    }
+
+    #[test]
+    fn link_bandwidth_and_delay() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_link(r1, r2)
+            .subnet("10.0.0.0/30")
+            .bandwidth(1544)
+            .delay(2000)
+            .finish();
+        app.interface_mut(r2, r1).unwrap().bandwidth = Some(512);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("   bandwidth 1544\n   delay 2000\n"));
+        assert!(commands["R2"].contains("   bandwidth 512\n   delay 2000\n"));
+    }
+
+    #[test]
+    fn device_roles_and_tags() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .role(Some(Role::Edge))
+            .tags(vec!["branch".to_string()])
+            .finish();
+        let r2 = app.add_device("R2").role(Some(Role::Core)).finish();
+        let r3 = app
+            .add_device("R3")
+            .role(Some(Role::Edge))
+            .tags(vec!["branch".to_string(), "lab".to_string()])
+            .finish();
+
+        let mut edge = app.devices_with_role(Role::Edge);
+        edge.sort();
+        assert_eq!(edge, vec![r1, r3]);
+        assert_eq!(app.devices_with_role(Role::Core), vec![r2]);
+        assert_eq!(app.devices_tagged("lab"), vec![r3]);
+        assert!(app.devices_tagged("campus").is_empty());
+    }
+
+    #[test]
+    fn sites() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app
+            .add_device("R2")
+            .ntp_server(Some("10.9.9.9".parse().unwrap()))
+            .finish();
+        let r3 = app.add_device("R3").finish();
+        app.set_address_pool("10.0.0.0/16", 30);
+        let branch = Site::new("Branch", &[r1, r2])
+            .domain_name(Some("branch.lab".into()))
+            .ntp_server(Some("10.0.0.100".parse().unwrap()))
+            .address_pool("10.0.0.0/24", 30);
+        app.add_site(branch);
+
+        let inside = app.allocate_subnet(r1, r2);
+        assert_eq!(inside, "10.0.0.0/30".parse().unwrap());
+        // The block of the site isn't used by the links outside of it
+        let outside = app.allocate_subnet(r2, r3);
+        assert_eq!(outside, "10.0.1.0/30".parse().unwrap());
+        app.link(r1, r2, &inside.to_string(), None);
+        app.link(r2, r3, &outside.to_string(), None);
+        assert_eq!(app.site_of(r1).unwrap().name, "Branch");
+        assert!(app.site_of(r3).is_none());
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("ip domain-name branch.lab\n\nntp server 10.0.0.100\n"));
+        assert!(commands["R2"].contains("ntp server 10.9.9.9\n"));
+        assert!(!commands["R3"].contains("ntp server"));
+    }
+
+    #[test]
+    fn ssh_with_site_domain() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").ssh(Some(Ssh::default())).finish();
+        let site = Site::new("Branch", &[r1]).domain_name(Some("branch.lab".into()));
+        app.add_site(site);
+
+        let commands = app.to_commands();
+        assert!(commands["R1"].contains("ip domain-name branch.lab\n"));
+        assert!(commands["R1"].contains("crypto key generate rsa general-keys modulus 1024\n"));
+    }
+
+    #[test]
+    fn toml_documents() {
+        let text = r#"
+            # The constraints of the lab
+            seed = 42
+            devices = 1_0
+            protocols = [
+                ["rip", 1],
+                ['eigrp', 2],   # trailing commas are allowed
+            ]
+        "#;
+        let topology: RandomTopology = toml::from_str(text).unwrap();
+        assert_eq!(topology.seed, 42);
+        assert_eq!(topology.devices, 10);
+        assert_eq!(topology.min_degree, 1);
+        assert_eq!(
+            topology.protocols,
+            vec![(RoutingProtocol::Rip, 1), (RoutingProtocol::Eigrp, 2)]
+        );
+
+        let text = r#"
+            description = """Uplink \
+              to the core"""
+            fhrp_priority = 110
+            ipv6_autoconfig = { mode = "slaac", domain_name = 'lab.local' }
+            port_security.sticky = true
+            [port_security]
+            maximum = 2
+        "#;
+        let options: InterfaceOptions = toml::from_str(text).unwrap();
+        assert_eq!(options.description.as_deref(), Some("Uplink to the core"));
+        assert_eq!(options.fhrp_priority, Some(110));
+        let autoconfig = options.ipv6_autoconfig.unwrap();
+        assert_eq!(autoconfig.mode, Ipv6AddressingMode::Slaac);
+        assert_eq!(autoconfig.domain_name.as_deref(), Some("lab.local"));
+        let port_security = options.port_security.unwrap();
+        assert!(port_security.sticky);
+        assert_eq!(port_security.maximum, Some(2));
+
+        let error = toml::from_str::<RandomTopology>("seed = 1\nseed = 2\n").unwrap_err();
+        assert_eq!(error, "line 2: the key `seed` is defined twice");
+        let error = toml::from_str::<RandomTopology>("devices = [1, 2\n").unwrap_err();
+        assert_eq!(error, "line 2: expected `,` or `]`");
+
+        // The keys which are numbers are read as numbers, as in YAML
+        let text = r#"
+            [devices.r1]
+            [devices.r2]
+
+            [[links]]
+            r1 = "r1"
+            r2 = "r2"
+            ip = "10.0.0.0/30"
+            ospf = 1
+
+            [ospf_areas]
+            1 = "stub"
+            "2" = "nssa"
+
+            [intervlan_routing]
+            gateway = "r1"
+            [intervlan_routing.vlans]
+            10 = "192.168.10.0/24"
+            20 = "192.168.20.0/24"
+        "#;
+        let document: TopologyFile = toml::from_str(text).unwrap();
+        assert_eq!(document.ospf_areas[&1], AreaType::Stub);
+        assert_eq!(document.ospf_areas[&2], AreaType::Nssa);
+        let vlans: Vec<_> = document.intervlan_routing.vlans.into_keys().collect();
+        assert_eq!(vlans, [10, 20]);
+    }
+
+    #[test]
+    fn csv_import() {
+        let devices = "name,kind,x,y\nR1,,0,0\nS1,switch,,\n\"R 2\" ,router,100,0\n";
+        let links = "r1, r2, ip, ospf\nR1,R 2,10.0.0.0/30,0\nR1,S1,192.168.0.1/24,\n";
+        let app = csv::import(devices, links).ok().unwrap();
+
+        let r1 = app.find_key_by_name("R1").unwrap();
+        let r2 = app.find_key_by_name("R 2").unwrap();
+        let s1 = app.find_key_by_name("S1").unwrap();
+        assert_eq!(app.devices[s1].kind, DeviceKind::Switch);
+        assert_eq!(app.devices[r2].x, 100.0);
+        assert_eq!(app.links_between(r1, s1).len(), 1);
+        let link = app.get_directed_link(r1, r2).unwrap();
+        assert_eq!(link.close_ip, "10.0.0.1/30".parse().unwrap());
+        assert_eq!(link.ospf_area, Some(0));
+
+        let devices = "name,kind\nR1\nR3,ruter\nR2\nR2\n";
+        let links = "r1,r2,ip\nR1,R3,10.0.0.0/30\nR1,R2,10.0.0.0/32\nR1,R2,\"10.0.0.4/30\n";
+        let errors = csv::import(devices, links).err().unwrap();
+        assert_eq!(
+            errors,
+            vec![
+                "devices.csv, line 3: invalid `kind` `ruter`",
+                "devices.csv, line 5: the device `R2` is declared twice",
+                "links.csv, line 4: unterminated quoted field",
+                "links.csv, line 2: unknown device `R3`",
+                "links.csv, line 3: the subnet `10.0.0.0/32` has less than two addresses",
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_frame_relay_cloud() {
+        let mut app = App::new();
+
+        let fr = app
+            .add_device("FR")
+            .kind(DeviceKind::FrameRelayCloud)
+            .finish();
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        let r3 = app.add_device("R3").finish();
+        app.frame_relay(fr, r1, r2, "10.0.12.0/30", None, [102, 201]);
+        app.link(r1, r3, "10.0.13.0/30", None);
+
+        app.remove_device(fr);
+        assert_eq!(app.links.len(), 1);
+        assert!(app.links_between(r1, r2).is_empty());
+        let commands = app.to_commands();
+        assert!(!commands["R1"].contains("frame-relay"));
+        assert!(!commands["R2"].contains("frame-relay"));
+    }
+
+    #[test]
+    fn remove_device_referenced_by_name() {
+        let mut app = App::new();
+
+        let r1 = app
+            .add_device("R1")
+            .logging(Some(Logging {
+                server: "SRV".into(),
+                level: None,
+                timestamps: false,
+            }))
+            .snmp(Some(Snmp {
+                trap_hosts: vec![SnmpTrapHost {
+                    server: "SRV".into(),
+                    community: "public".into(),
+                }],
+                ..Default::default()
+            }))
+            .finish();
+        let r2 = app.add_device("R2").finish();
+        let srv = app.add_device("SRV").kind(DeviceKind::Server).finish();
+        app.link(r1, srv, "10.0.0.0/30", None);
+        app.link(r1, r2, "10.0.1.0/24", None);
+        let lan = app.interface_mut(r1, r2).unwrap();
+        lan.dhcp_relay = Some(DhcpRelay::Device("SRV".into()));
+        lan.fhrp_track = Some(FhrpTrack {
+            towards: "SRV".into(),
+            decrement: 20,
+        });
+
+        app.remove_device(srv);
+        assert!(app.devices[r1].logging.is_none());
+        assert!(app.devices[r1].snmp.as_ref().unwrap().trap_hosts.is_empty());
+        let lan = app.interface_mut(r1, r2).unwrap();
+        assert!(lan.dhcp_relay.is_none() && lan.fhrp_track.is_none());
+        let commands = app.to_commands();
+        assert!(!commands["R1"].contains("ip helper-address"));
+        assert!(!commands["R1"].contains("logging host"));
+    }
+
+    #[test]
+    fn role_and_tag_defaults() {
+        let mut app = App::new();
+
+        let edge_acl = Acl {
+            name: "EDGE-IN".into(),
+            rules: vec![],
+        };
+        let r1 = app.add_device("R1").role(Some(Role::Edge)).finish();
+        let r2 = app
+            .add_device("R2")
+            .role(Some(Role::Edge))
+            .tags(vec!["lab".into()])
+            .banner(Some("Own banner".into()))
+            .finish();
+        let r3 = app.add_device("R3").role(Some(Role::Core)).finish();
+
+        app.apply_role_defaults(
+            Role::Edge,
+            &DeviceDefaults {
+                acls: vec![edge_acl.clone()],
+                banner: Some("Edge router".into()),
+                ..Default::default()
+            },
+        );
+        app.apply_tag_defaults(
+            "lab",
+            &DeviceDefaults {
+                acls: vec![Acl {
+                    name: "EDGE-IN".into(),
+                    rules: vec![AclRule::default()],
+                }],
+                domain_name: Some("lab.local".into()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(app.devices[r1].acls, [edge_acl.clone()].as_slice());
+        assert_eq!(app.devices[r1].banner.as_deref(), Some("Edge router"));
+        // The settings of the device, and the ACLs it already has, win
+        assert_eq!(app.devices[r2].acls, [edge_acl]);
+        assert_eq!(app.devices[r2].banner.as_deref(), Some("Own banner"));
+        assert_eq!(
+            app.devices[r2].dns.domain_name.as_deref(),
+            Some("lab.local")
+        );
+        assert!(app.devices[r3].acls.is_empty() && app.devices[r3].banner.is_none());
+    }
+
+    /// The parts of the topology files which are maps keyed by numbers
+    #[derive(Deserialize)]
+    struct TopologyFile {
+        ospf_areas: HashMap<u16, AreaType>,
+        intervlan_routing: TopologyVlans,
+    }
+
+    #[derive(Deserialize)]
+    struct TopologyVlans {
+        vlans: BTreeMap<u16, String>,
+    }
+
+    #[test]
+    fn json_documents() {
+        let text = r#"{
+            "seed": 42,
+            "devices": 10,
+            "protocols": [["rip", 1], ["eigrp", 2]]
+        }"#;
+        let topology: RandomTopology = json::from_str(text).unwrap();
+        assert_eq!(topology.seed, 42);
+        assert_eq!(topology.devices, 10);
+        assert_eq!(
+            topology.protocols,
+            vec![(RoutingProtocol::Rip, 1), (RoutingProtocol::Eigrp, 2)]
+        );
+
+        let text = r#"{"description": "Uplink\tto \"core\" \ud83d\ude80", "fhrp_priority": null}"#;
+        let options: InterfaceOptions = json::from_str(text).unwrap();
+        assert_eq!(
+            options.description.as_deref(),
+            Some("Uplink\tto \"core\" \u{1f680}")
+        );
+        assert_eq!(options.fhrp_priority, None);
+
+        // Valid YAML, but not JSON
+        let error = json::from_str::<RandomTopology>("seed: 42\n").unwrap_err();
+        assert_eq!(error, "line 1: expected a value");
+        let error = json::from_str::<RandomTopology>("{\"seed\": 42,\n}").unwrap_err();
+        assert_eq!(error, "line 2: expected a key");
+        let error = json::from_str::<RandomTopology>("{\"seed\": 042}").unwrap_err();
+        assert_eq!(error, "line 1: expected `,` or `}`");
+        let error = json::from_str::<RandomTopology>("{\"seed\": 1, \"seed\": 2}").unwrap_err();
+        assert_eq!(error, "line 1: the key `seed` is defined twice");
+
+        // The keys which are numbers are read as numbers, as in YAML
+        let text = r#"{
+            "devices": {"r1": {}, "r2": {}},
+            "links": [{"r1": "r1", "r2": "r2", "ip": "10.0.0.0/30", "ospf": 1}],
+            "ospf_areas": {"1": "stub", "2": "nssa"},
+            "intervlan_routing": {"gateway": "r1", "vlans": {"10": "192.168.10.0/24"}}
+        }"#;
+        let document: TopologyFile = json::from_str(text).unwrap();
+        assert_eq!(document.ospf_areas[&1], AreaType::Stub);
+        assert_eq!(document.intervlan_routing.vlans[&10], "192.168.10.0/24");
+        let yaml = "{ospf_areas: {1: stub, 2: nssa}, intervlan_routing: {vlans: {10: x}}}";
+        assert_eq!(
+            document.ospf_areas,
+            serde_yaml::from_str::<TopologyFile>(yaml)
+                .unwrap()
+                .ospf_areas
+        );
+    }
+
+    #[test]
+    fn ssh_vty_lines() {
+        let mut app = App::new();
+
+        let ssh = |vty_lines| {
+            Some(Ssh {
+                vty_lines,
+                ..Default::default()
+            })
+        };
+        let r1 = app.add_device("R1").ssh(ssh(0)).finish();
+        let r2 = app.add_device("R2").ssh(ssh(17)).finish();
+        app.add_device("R3").ssh(ssh(16)).finish();
+        app.add_device("R4").ssh(ssh(1)).finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+
+        let issues: Vec<_> = app
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.code == IssueCode::VtyLines)
+            .map(|issue| issue.message)
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "Device `R1` accepts SSH on 0 VTY lines, but it must be between 1 and 16",
+                "Device `R2` accepts SSH on 17 VTY lines, but it must be between 1 and 16",
+            ]
+        );
+        assert_eq!(app.devices[r1].ssh.as_ref().unwrap().last_vty_line(), 0);
+    }
+
+    #[test]
+    fn unreachable_servers() {
+        let mut app = App::new();
+
+        let r1 = app.add_device("R1").finish();
+        let r2 = app.add_device("R2").finish();
+        app.add_device("PC1").kind(DeviceKind::Pc).finish();
+        app.link(r1, r2, "10.0.0.0/30", None);
+        app.interface_mut(r1, r2).unwrap().dhcp_relay = Some(DhcpRelay::Device("SRV".into()));
+        app.interface_mut(r2, r1).unwrap().dhcp_relay = Some(DhcpRelay::Device("PC1".into()));
+
+        let issues: Vec<_> = app
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.code == IssueCode::UnreachableServer)
+            .map(|issue| (issue.severity, issue.devices, issue.message))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (
+                    Severity::Error,
+                    vec!["R1".to_string()],
+                    "Device `R1` relays DHCP requests to `SRV`, but there is no device named `SRV`"
+                        .to_string()
+                ),
+                (
+                    Severity::Error,
+                    vec!["R2".to_string()],
+                    "Device `R2` relays DHCP requests to `PC1`, but `PC1` has no links to be reached at"
+                        .to_string()
+                ),
+            ]
+        );
+        // The configurations can still be generated, without the relays
+        assert!(!app.to_commands()["R1"].contains("ip helper-address"));
+
+        app.devices[r1].logging = Some(Logging {
+            server: "PC1".into(),
+            level: None,
+            timestamps: false,
+        });
+        let issue = app
+            .validate()
+            .into_iter()
+            .find(|issue| issue.message.contains("logs to"))
+            .unwrap();
+        assert_eq!(issue.code, IssueCode::UnreachableServer);
+        assert_eq!(
+            issue.message,
+            "Device `R1` logs to `PC1`, but `PC1` has no links to be reached at"
+        );
+        assert!(!app.to_commands()["R1"].contains("logging host"));
+
+        app.devices[r2].snmp = Some(Snmp {
+            trap_hosts: vec![SnmpTrapHost {
+                server: "NMS".into(),
+                community: "public".into(),
+            }],
+            ..Default::default()
+        });
+        let issue = app
+            .validate()
+            .into_iter()
+            .find(|issue| issue.message.contains("SNMP traps"))
+            .unwrap();
+        assert_eq!(issue.code, IssueCode::UnreachableServer);
+        assert_eq!(issue.devices, ["R2"]);
+        assert_eq!(
+            issue.message,
+            "Device `R2` sends SNMP traps to `NMS`, but there is no device named `NMS`"
+        );
+        assert!(!app.to_commands()["R2"].contains("snmp-server host"));
+    }
 }
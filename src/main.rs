@@ -1,31 +1,372 @@
-use std::{collections::HashMap, io::{ErrorKind, Write}, fs::File};
+use std::{collections::{BTreeMap, HashMap}, io::{ErrorKind, Write}, fs::File, path::Path, net::{IpAddr, Ipv4Addr}};
 
-use packet_tracer_generator::{App, Redistributions};
+use packet_tracer_generator::{
+    Aaa, Acl, App, AreaType, Bfd, BgpPolicy, ClassMap, Config, DefaultRoute, DeviceDefaults,
+    DeviceKind, DhcpSnooping, Direction, Distances, DistributeList, DnsSettings, EigrpKValues,
+    FhrpGroup, InterfaceOptions, InterfaceType, IsisLevel, KeyChain, LocalUser, Logging, Multicast,
+    Numbering, OspfNetworkType, Passwords, Platform, PolicyMap, Ppp, PrefixList, RandomTopology,
+    Redistributions, RipSettings, Role, RouteMap, Services, Severity, Site, Snmp, SpanningTree, Ssh,
+    Summary, SummaryProtocol, Svi, Trunk, Vlan, Vpn, VpnPeer, Vrf, Vtp, csv, json, toml,
+};
 
+use ipnet::IpNet;
 use linked_hash_map::LinkedHashMap;
 use serde::Deserialize;
 
 fn main() {
-    let commands = std::fs::read_to_string("commands.yml").expect("Error opening file `commands.yml`");
+    // The topology is read from the file given as argument, or else from `commands.{yml,json,toml}`
+    let path = std::env::args().nth(1).unwrap_or_else(|| {
+        let default = ["commands.yml", "commands.json", "commands.toml"].into_iter().find(|path| Path::new(path).exists());
+        default.unwrap_or("commands.yml").to_string()
+    });
 
-    let mut app = App::new();
-    let mut keys = HashMap::new();
-    let document = serde_yaml::from_str::<Document>(&commands).expect("`commands.yml` is not valid YAML");
+    // A directory holds the topology as the `devices.csv` and `links.csv` spreadsheets
+    let (mut document, mut app) = if Path::new(&path).is_dir() {
+        (Document::default(), import_csv(&path))
+    } else {
+        let commands = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("Error opening file `{path}`"));
+        let document = parse_document(&path, &commands);
+        // The devices declared in the document are added to the random topology, if any
+        let app = document.random.as_ref().map_or_else(App::new, RandomTopology::generate);
+        (document, app)
+    };
+    let mut keys: HashMap<_, _> = app.devices.iter().map(|(key, device)| (device.name.clone(), key)).collect();
+    // The imported devices are already placed, while the random ones are placed once the links are known
+    let mut unplaced: Vec<_> = if document.random.is_some() { keys.values().copied().collect() } else { vec![] };
 
     for (ref name, device) in &document.devices {
-        keys.insert(
-            name.to_string(),
-            app.add_device(name)
-                .position(device.x, device.y)
-                .redistribute_ospf_to_rip(device.redistributions.ospf_to_rip)
-                .finish(),
-        );
+        let mut builder = app.add_device(name)
+            .kind(device.kind)
+            .platform(device.platform)
+            .numbering(device.numbering.clone())
+            .ntp_server(device.ntp_server)
+            .role(device.role)
+            .tags(device.tags.clone())
+            .position(device.x.unwrap_or_default(), device.y.unwrap_or_default())
+            .redistributions(device.redistributions.clone())
+            .distances(device.distances)
+            .multicast(device.multicast.clone())
+            .vtp(device.vtp.clone())
+            .spanning_tree(device.spanning_tree.clone())
+            .rip_version(device.rip.version)
+            .rip_auto_summary(device.rip.auto_summary)
+            .dns(device.dns.clone())
+            .logging(device.logging.clone())
+            .snmp(device.snmp.clone())
+            .aaa(device.aaa.clone())
+            .ssh(device.ssh.clone())
+            .cdp(device.cdp)
+            .lldp(device.lldp)
+            .passwords(device.passwords.clone())
+            .banner(device.banner.clone())
+            .eigrp_as(device.eigrp)
+            .eigrp_variance(device.eigrp_variance)
+            .eigrp_k_values(device.eigrp_k_values)
+            .bgp_as(device.bgp)
+            .isis_net(device.isis_net.clone())
+            .originate_default_route(device.originate_default_route)
+            .ospf_router_id(device.ospf_router_id)
+            .default_gateway(device.default_gateway)
+            .dns_server(device.dns_server)
+            .services(device.services.clone())
+            .ospf_mutual_redistribution(device.ospf_mutual_redistribution);
+
+        for key_chain in &device.key_chains {
+            builder = builder.key_chain(key_chain.clone());
+        }
+
+        for user in &device.users {
+            builder = builder.user(user.clone());
+        }
+
+        for vrf in &device.vrfs {
+            builder = builder.vrf(vrf.clone());
+        }
+
+        for loopback in &device.loopbacks {
+            builder = builder.add_loopback(loopback);
+        }
+
+        for vlan in &device.vlans {
+            builder = builder.vlan(vlan.clone());
+        }
+
+        for svi in &device.svis {
+            builder = builder.svi(svi.clone());
+        }
+
+        for route_map in &device.route_maps {
+            builder = builder.route_map(route_map.clone());
+        }
+
+        for prefix_list in &device.prefix_lists {
+            builder = builder.prefix_list(prefix_list.clone());
+        }
+
+        for acl in &device.acls {
+            builder = builder.acl(acl.clone());
+        }
+
+        for class_map in &device.class_maps {
+            builder = builder.class_map(class_map.clone());
+        }
+
+        for policy_map in &device.policy_maps {
+            builder = builder.policy_map(policy_map.clone());
+        }
+
+        for distribute_list in &device.distribute_lists {
+            builder = builder.distribute_list(distribute_list.clone());
+        }
+
+        for route in &device.static_routes {
+            builder = builder.floating_static_route(&route.prefix, &route.next_hop, route.distance);
+        }
+
+        let key = builder.finish();
+        keys.insert(name.to_string(), key);
+        if device.x.is_none() || device.y.is_none() {
+            unplaced.push(key);
+        }
+    }
+
+    // The defaults of the roles and tags fill in what the devices don't set
+    for (&role, defaults) in &document.role_defaults {
+        app.apply_role_defaults(role, defaults);
+    }
+    for (tag, defaults) in &document.tag_defaults {
+        app.apply_tag_defaults(tag, defaults);
+    }
+
+    // Default routes can reference devices declared later, so they are set once all keys are known
+    for (name, device) in &document.devices {
+        let default_route = match &device.default_route {
+            Some(DefaultRouteDef::NextHop(ip)) => {
+                DefaultRoute::NextHop(ip.parse().unwrap_or_else(|_| panic!("Failed to parse ip: {ip}")))
+            }
+            Some(DefaultRouteDef::ExitTowards(far)) => DefaultRoute::ExitInterface(keys[far.as_str()]),
+            None => continue,
+        };
+        app.get_device(name).unwrap().default_route = Some(default_route);
+    }
+
+    for (name, device) in &document.devices {
+        let clients = device.bgp_rr_clients.iter().map(|client| keys[client.as_str()]).collect();
+        let policies = device
+            .bgp_policies
+            .iter()
+            .map(|policy| BgpPolicy {
+                neighbor: keys[policy.neighbor.as_str()],
+                direction: policy.direction,
+                local_preference: policy.local_preference,
+                med: policy.med,
+                community: policy.community.clone(),
+            })
+            .collect();
+
+        let summaries = device
+            .summaries
+            .iter()
+            .map(|summary| Summary {
+                protocol: match (summary.protocol.as_str(), summary.area, &summary.towards) {
+                    ("ospf", Some(area), _) => SummaryProtocol::Ospf { area },
+                    ("eigrp", _, Some(towards)) => SummaryProtocol::Eigrp { towards: keys[towards.as_str()] },
+                    ("rip", _, Some(towards)) => SummaryProtocol::Rip { towards: keys[towards.as_str()] },
+                    _ => panic!("Invalid summary on device `{name}`: {summary:?}"),
+                },
+                prefix: summary
+                    .prefix
+                    .as_ref()
+                    .map(|prefix| prefix.parse().unwrap_or_else(|_| panic!("Failed to parse prefix: {prefix}"))),
+            })
+            .collect();
+
+        let dhcp_snooping = device.dhcp_snooping.as_ref().map(|snooping| DhcpSnooping {
+            vlans: snooping.vlans.clone(),
+            server: keys[snooping.server.as_str()],
+        });
+
+        let dev = app.get_device(name).unwrap();
+        dev.dhcp_snooping = dhcp_snooping;
+        dev.bgp_rr_clients = clients;
+        dev.bgp_policies = policies;
+        dev.summaries = summaries;
+    }
+
+    app.set_default_passwords(document.passwords.clone());
+    if let Some(banner) = &document.banner {
+        app.set_default_banner(banner);
+    }
+
+    for (&area, &area_type) in &document.ospf_areas {
+        app.set_area_type(area, area_type);
+    }
+
+    if let Some(auth) = &document.ospf_authentication {
+        app.set_ospf_md5_key(&auth.key);
+        for &area in &auth.areas {
+            app.authenticate_area(area);
+        }
+    }
+
+    // Links can use the name of a VLSM segment as their ip
+    let mut segments = HashMap::new();
+    if let Some(vlsm) = &document.vlsm {
+        let hosts: Vec<(&str, u32)> = vlsm.segments.iter().map(|(name, &hosts)| (name.as_str(), hosts)).collect();
+        let subnets = app.plan_vlsm(&vlsm.block, &hosts);
+        for (&(name, _), subnet) in hosts.iter().zip(subnets) {
+            segments.insert(name.to_string(), subnet.to_string());
+        }
+    }
+    for link in &mut document.links {
+        if let Some(subnet) = link.ip.as_ref().and_then(|ip| segments.get(ip)) {
+            link.ip = Some(subnet.clone());
+        }
+    }
+
+    if let Some(pool) = &document.address_pool {
+        app.set_address_pool(&pool.pool, pool.prefix_len);
+    }
+    for (name, site) in &document.sites {
+        let members: Vec<_> = site.members.iter().map(|member| keys[member.as_str()]).collect();
+        let mut builder = Site::new(name, &members)
+            .domain_name(site.domain_name.clone())
+            .ntp_server(site.ntp_server);
+        if let Some(pool) = &site.address_pool {
+            builder = builder.address_pool(&pool.pool, pool.prefix_len);
+        }
+        app.add_site(builder);
+    }
+    // The links declared later mustn't get subnets assigned by hand
+    for link in &document.links {
+        match link.ip.as_deref() {
+            Some("auto") | None => {}
+            Some(ip) => app.reserve_subnet(ip),
+        }
     }
 
-    for link in document.links {
+    for mut link in document.links {
         let r1 = link.r1.as_str();
         let r2 = link.r2.as_str();
-        app.link(keys[r1], keys[r2], &link.ip, link.ospf);
+        if link.ip.as_deref() == Some("auto") {
+            link.ip = Some(app.allocate_subnet(keys[r1], keys[r2]).to_string());
+        }
+        let l = match (&link.frame_relay, &link.ip) {
+            (Some(_), _) if link.serial.is_some() => panic!("The Frame Relay PVC between {r1} and {r2} can't be a serial link"),
+            // The first router of a PVC is its hub
+            (Some(pvc), Some(ip)) => {
+                let dlcis = [pvc.hub_dlci, pvc.spoke_dlci];
+                let cloud = keys[pvc.cloud.as_str()];
+                app.frame_relay(cloud, keys[r1], keys[r2], ip, link.ospf, dlcis)
+            }
+            (Some(_), None) => panic!("The Frame Relay PVC between {r1} and {r2} needs a subnet"),
+            // Links between the same routers are parallel, rather than replacing each other
+            (None, ip) => {
+                let mut builder = app.add_link(keys[r1], keys[r2]);
+                // Links without a subnet borrow the addresses of the loopbacks
+                if let Some(ip) = ip {
+                    builder = builder.subnet(ip);
+                }
+                if let Some(ip) = link.r1_address {
+                    builder = builder.address(keys[r1], ip);
+                }
+                if let Some(ip) = link.r2_address {
+                    builder = builder.address(keys[r2], ip);
+                }
+                if let Some(area) = link.ospf {
+                    builder = builder.ospf_area(area);
+                }
+                if let Some(serial) = &link.serial {
+                    builder = builder.interface_type(InterfaceType::Serial {
+                        dce: keys[serial.dce.as_str()],
+                        clock_rate: serial.clock_rate,
+                    });
+                }
+                builder.finish()
+            }
+        };
+        l.ospf_cost = link.ospf_cost;
+        l.ospf_auth = link.ospf_authentication;
+        l.eigrp = link.eigrp;
+        l.bgp = link.bgp;
+        l.isis = link.isis;
+        l.pim = link.pim;
+        l.fhrp = link.fhrp;
+        l.bfd = link.bfd;
+        l.ospf_network_type = link.ospf_network_type;
+        l.ospf_process = link.ospf_process;
+        l.trunk = link.trunk;
+        l.access_vlan = link.access_vlan;
+        l.voice_vlan = link.voice_vlan;
+        l.ppp = link.ppp;
+        l.mtu = link.mtu;
+        l.bandwidth = link.bandwidth;
+        l.delay = link.delay;
+        l.eui64 = link.eui64;
+
+        if let Some(ipv6) = &link.ipv6 {
+            app.dual_stack(keys[r1], keys[r2], ipv6);
+        }
+
+        for subinterface in &link.subinterfaces {
+            app.subinterface(keys[r1], keys[r2], subinterface.vlan, &subinterface.ip);
+        }
+
+        if let Some(channel) = link.etherchannel {
+            app.bundle(keys[r1], keys[r2], channel.group, channel.members);
+        }
+
+        for (close, far, mut options) in [(r1, r2, link.r1_interface), (r2, r1, link.r2_interface)] {
+            let interface = app.interface_mut(keys[close], keys[far]).unwrap();
+            // The interfaces of the links without a subnet were made unnumbered by the builder
+            options.unnumbered = options.unnumbered.or(interface.unnumbered);
+            *interface = options;
+        }
+    }
+
+    for segment in &document.segments {
+        let members: Vec<_> = segment.members.iter().map(|name| keys[name.as_str()]).collect();
+        app.segment(keys[segment.switch.as_str()], &members, &segment.subnet, segment.ospf);
+    }
+
+    // Templates come after the links, so that the subnets assigned by hand are reserved
+    for template in &document.templates {
+        let (prefix, n, area) = (template.prefix.as_str(), template.count, template.ospf);
+        let devices = match template.shape {
+            TemplateShape::Ring => app.template_ring(prefix, n, area),
+            TemplateShape::Star => app.template_star(prefix, n, area),
+            TemplateShape::FullMesh => app.template_full_mesh(prefix, n, area),
+            TemplateShape::HubAndSpoke => app.template_hub_and_spoke(prefix, n, area),
+        };
+        for key in devices {
+            keys.insert(app.devices[key].name.clone(), key);
+            unplaced.push(key);
+        }
+    }
+
+    for vpn in &document.vpns {
+        let peers = vpn.peers.each_ref().map(|peer| VpnPeer {
+            router: keys[peer.router.as_str()],
+            outside: keys[peer.outside.as_str()],
+            networks: peer.networks.clone(),
+        });
+        app.add_vpn(Vpn { pre_shared_key: vpn.pre_shared_key.clone(), peers });
+    }
+
+    if let Some(routing) = &document.intervlan_routing {
+        let vlans: Vec<_> = routing.vlans.iter().map(|(&vlan, subnet)| (vlan, subnet.as_str())).collect();
+        app.setup_intervlan_routing(keys[routing.gateway.as_str()], &vlans);
+    }
+
+    // The layout depends on the links, so it's computed once they are all known
+    app.auto_layout(&unplaced);
+
+    let issues = app.validate();
+    for issue in &issues {
+        eprintln!("{issue}");
+    }
+    if issues.iter().any(|issue| issue.severity == Severity::Error) {
+        panic!("The topology has errors, no configuration was generated");
     }
 
     match std::fs::create_dir("output").map_err(|e| e.kind()) {
@@ -33,29 +374,167 @@ fn main() {
         Err(e) => panic!("Cannot create dir `output`: {:?}", e), 
     }
 
-    for (dev_name, commands) in app.to_commands() {
-        let filename = format!("output/{dev_name}.txt");
+    for (dev_name, config) in app.to_commands() {
+        // Host settings are entered by hand, so they shouldn't look like a script
+        let filename = match config {
+            Config::Cli(_) => format!("output/{dev_name}.txt"),
+            Config::HostSettings(_) => format!("output/{dev_name}.settings.txt"),
+        };
 
-        let mut file = File::create(&filename).expect(&format!("Failed to create file {filename}"));
-        file.write_all(commands.as_bytes()).expect(&format!("Failed to write to file {filename}"));
+        let mut file = File::create(&filename).unwrap_or_else(|_| panic!("Failed to create file {filename}"));
+        file.write_all(config.as_bytes()).unwrap_or_else(|_| panic!("Failed to write to file {filename}"));
         drop(file);
 
         println!("Written file `{filename}`");
     }
+
+    let allocations = app.allocation_table();
+    if !allocations.is_empty() {
+        let filename = "output/allocations.txt";
+        std::fs::write(filename, allocations).unwrap_or_else(|_| panic!("Failed to write to file {filename}"));
+        println!("Written file `{filename}`");
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct Document {
     devices: LinkedHashMap<String, Router>,
     links: Vec<Link>,
+    #[serde(default)]
+    ospf_areas: HashMap<u16, AreaType>,
+    ospf_authentication: Option<OspfAuthentication>,
+    /// The passwords of the devices which don't set their own
+    #[serde(default)]
+    passwords: Passwords,
+    /// The banner of the devices which don't set their own
+    banner: Option<String>,
+    intervlan_routing: Option<InterVlanRouting>,
+    /// The block the links with an `auto` ip get their subnet from
+    address_pool: Option<AddressPoolDef>,
+    vlsm: Option<VlsmDef>,
+    #[serde(default)]
+    vpns: Vec<VpnDef>,
+    /// The LANs shared by more than two devices
+    #[serde(default)]
+    segments: Vec<SegmentDef>,
+    /// The settings shared by the devices with each role
+    #[serde(default)]
+    role_defaults: LinkedHashMap<Role, DeviceDefaults>,
+    /// The settings shared by the devices with each tag
+    #[serde(default)]
+    tag_defaults: LinkedHashMap<String, DeviceDefaults>,
+    /// The groups of devices sharing the same defaults
+    #[serde(default)]
+    sites: LinkedHashMap<String, SiteDef>,
+    /// The constraints of a random topology, to generate practice labs
+    random: Option<RandomTopology>,
+    /// Standard topologies, whose routers and links are generated
+    #[serde(default)]
+    templates: Vec<TemplateDef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SegmentDef {
+    switch: String,
+    subnet: String,
+    /// The devices attached to the switch, which get the addresses of the subnet in order
+    members: Vec<String>,
+    ospf: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateDef {
+    shape: TemplateShape,
+    /// The routers are named from `{prefix}1` to `{prefix}{count}`
+    prefix: String,
+    count: usize,
+    ospf: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TemplateShape {
+    Ring,
+    Star,
+    FullMesh,
+    HubAndSpoke,
+}
+
+#[derive(Debug, Deserialize)]
+struct VpnDef {
+    pre_shared_key: String,
+    peers: [VpnPeerDef; 2],
+}
+
+#[derive(Debug, Deserialize)]
+struct VpnPeerDef {
+    router: String,
+    outside: String,
+    networks: Vec<IpNet>,
+}
+
+/// The VLANs routed by `gateway`, with their subnets
+#[derive(Debug, Deserialize)]
+struct InterVlanRouting {
+    gateway: String,
+    vlans: BTreeMap<u16, String>,
 }
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 struct Router {
+    kind: DeviceKind,
+    platform: Option<Platform>,
+    numbering: Option<Numbering>,
+    ntp_server: Option<IpAddr>,
+    role: Option<Role>,
+    tags: Vec<String>,
     redistributions: Redistributions,
-    x: f32,
-    y: f32,
+    distances: Distances,
+    rip: RipSettings,
+    dns: DnsSettings,
+    logging: Option<Logging>,
+    snmp: Option<Snmp>,
+    users: Vec<LocalUser>,
+    aaa: Option<Aaa>,
+    ssh: Option<Ssh>,
+    cdp: Option<bool>,
+    lldp: Option<bool>,
+    passwords: Passwords,
+    banner: Option<String>,
+    key_chains: Vec<KeyChain>,
+    vrfs: Vec<Vrf>,
+    loopbacks: Vec<String>,
+    vlans: Vec<Vlan>,
+    svis: Vec<Svi>,
+    vtp: Option<Vtp>,
+    spanning_tree: SpanningTree,
+    dhcp_snooping: Option<DhcpSnoopingDef>,
+    multicast: Option<Multicast>,
+    route_maps: Vec<RouteMap>,
+    prefix_lists: Vec<PrefixList>,
+    acls: Vec<Acl>,
+    class_maps: Vec<ClassMap>,
+    policy_maps: Vec<PolicyMap>,
+    distribute_lists: Vec<DistributeList>,
+    x: Option<f32>,
+    y: Option<f32>,
+    eigrp: Option<u16>,
+    eigrp_variance: Option<u8>,
+    eigrp_k_values: Option<EigrpKValues>,
+    bgp: Option<u32>,
+    bgp_rr_clients: Vec<String>,
+    bgp_policies: Vec<BgpPolicyDef>,
+    summaries: Vec<SummaryDef>,
+    isis_net: Option<String>,
+    static_routes: Vec<StaticRoute>,
+    default_route: Option<DefaultRouteDef>,
+    originate_default_route: bool,
+    ospf_router_id: Option<Ipv4Addr>,
+    default_gateway: Option<IpAddr>,
+    dns_server: Option<IpAddr>,
+    services: Services,
+    ospf_mutual_redistribution: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,5 +542,163 @@ struct Link {
     r1: String,
     r2: String,
     ospf: Option<u16>,
+    ospf_cost: Option<u16>,
+    #[serde(default)]
+    ospf_authentication: bool,
+    #[serde(default)]
+    eigrp: bool,
+    #[serde(default)]
+    bgp: bool,
+    isis: Option<IsisLevel>,
+    ip: Option<String>,
+    /// The addresses of the two sides, if not the first ones of the subnet
+    r1_address: Option<IpAddr>,
+    r2_address: Option<IpAddr>,
+    /// The IPv6 subnet of a dual-stack link, whose `ip` is IPv4
+    ipv6: Option<String>,
+    #[serde(default)]
+    r1_interface: InterfaceOptions,
+    #[serde(default)]
+    r2_interface: InterfaceOptions,
+    #[serde(default)]
+    pim: bool,
+    fhrp: Option<FhrpGroup>,
+    bfd: Option<Bfd>,
+    ospf_network_type: Option<OspfNetworkType>,
+    ospf_process: Option<u16>,
+    trunk: Option<Trunk>,
+    access_vlan: Option<u16>,
+    etherchannel: Option<EtherChannelDef>,
+    #[serde(default)]
+    subinterfaces: Vec<SubinterfaceDef>,
+    voice_vlan: Option<u16>,
+    serial: Option<SerialDef>,
+    frame_relay: Option<FrameRelayDef>,
+    ppp: Option<Ppp>,
+    mtu: Option<u16>,
+    bandwidth: Option<u32>,
+    delay: Option<u32>,
+    #[serde(default)]
+    eui64: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FrameRelayDef {
+    cloud: String,
+    hub_dlci: u16,
+    spoke_dlci: u16,
+}
+
+#[derive(Debug, Deserialize)]
+struct SerialDef {
+    dce: String,
+    #[serde(default = "default_clock_rate")]
+    clock_rate: u32,
+}
+
+/// Import the topology from the CSV files in `dir`, reporting every malformed row
+fn import_csv(dir: &str) -> App {
+    let read = |name| {
+        let path = Path::new(dir).join(name);
+        std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("Error opening file `{}`", path.display()))
+    };
+    csv::import(&read("devices.csv"), &read("links.csv")).unwrap_or_else(|errors| {
+        for error in &errors {
+            eprintln!("{error}");
+        }
+        panic!("The CSV files have errors, no configuration was generated");
+    })
+}
+
+/// Parse the topology in the format given by the extension of its file, which is YAML if unknown
+fn parse_document(path: &str, commands: &str) -> Document {
+    match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some("json") => json::from_str(commands).unwrap_or_else(|e| panic!("`{path}` is not valid JSON: {e}")),
+        Some("toml") => toml::from_str(commands).unwrap_or_else(|e| panic!("`{path}` is not valid TOML: {e}")),
+        _ => serde_yaml::from_str(commands).unwrap_or_else(|e| panic!("`{path}` is not valid YAML: {e}")),
+    }
+}
+
+fn default_clock_rate() -> u32 {
+    64000
+}
+
+#[derive(Debug, Deserialize)]
+struct SiteDef {
+    members: Vec<String>,
+    domain_name: Option<String>,
+    ntp_server: Option<IpAddr>,
+    /// The block the links with an `auto` ip inside the site get their subnet from
+    address_pool: Option<AddressPoolDef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddressPoolDef {
+    pool: String,
+    prefix_len: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct VlsmDef {
+    block: String,
+    /// The number of hosts of every segment, by name
+    segments: LinkedHashMap<String, u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubinterfaceDef {
+    vlan: u16,
     ip: String,
 }
+
+#[derive(Debug, Deserialize)]
+struct EtherChannelDef {
+    group: u16,
+    members: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct StaticRoute {
+    prefix: String,
+    next_hop: String,
+    distance: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DefaultRouteDef {
+    NextHop(String),
+    ExitTowards(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct OspfAuthentication {
+    key: String,
+    #[serde(default)]
+    areas: Vec<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DhcpSnoopingDef {
+    vlans: Vec<u16>,
+    server: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BgpPolicyDef {
+    neighbor: String,
+    direction: Direction,
+    local_preference: Option<u32>,
+    med: Option<u32>,
+    community: Option<String>,
+}
+
+/// A summary: OSPF summaries need an `area`, EIGRP and RIP
+/// ones the neighbor they are advertised `towards`
+#[derive(Debug, Deserialize)]
+struct SummaryDef {
+    protocol: String,
+    area: Option<u16>,
+    towards: Option<String>,
+    prefix: Option<String>,
+}
@@ -0,0 +1,245 @@
+//! The services used to monitor and manage the devices
+
+use std::fmt::Write;
+
+use crate::{
+    hostname, AaaMethod, AaaMethodList, DeviceContext, DirectedLink, Ppp, SnmpAccess, Ssh,
+};
+
+pub(crate) fn write_passwords(ctx: &DeviceContext, res: &mut String) {
+    let own = &ctx.device.passwords;
+    let default = &ctx.app.default_passwords;
+    let enable_secret = own
+        .enable_secret
+        .as_ref()
+        .or(default.enable_secret.as_ref());
+    let console = own.console.as_ref().or(default.console.as_ref());
+    let vty = own.vty.as_ref().or(default.vty.as_ref());
+
+    if let Some(secret) = enable_secret {
+        writeln!(res, "enable secret {secret}").unwrap();
+    }
+    if let Some(password) = console {
+        writeln!(
+            res,
+            "line console 0\n   password {password}\n   login\nexit"
+        )
+        .unwrap();
+    }
+    if let Some(password) = vty {
+        let last = ctx.device.ssh.as_ref().map_or(4, Ssh::last_vty_line);
+        writeln!(
+            res,
+            "line vty 0 {last}\n   password {password}\n   login\nexit"
+        )
+        .unwrap();
+    }
+    if enable_secret.is_some() || console.is_some() || vty.is_some() {
+        res.push('\n');
+    }
+}
+
+pub(crate) fn write_banner(ctx: &DeviceContext, res: &mut String) {
+    let banner = ctx
+        .device
+        .banner
+        .as_ref()
+        .or(ctx.app.default_banner.as_ref());
+    let Some(banner) = banner else {
+        return;
+    };
+
+    let banner = banner.replace("{name}", &ctx.device.name);
+    // The banner ends at the first occurrence of the delimiter
+    let delimiter = ['^', '#', '%', '$', '@', '~']
+        .into_iter()
+        .find(|&c| !banner.contains(c))
+        .expect("The banner must not use all of `^#%$@~`");
+    writeln!(res, "banner motd {delimiter}{banner}{delimiter}\n").unwrap();
+}
+
+pub(crate) fn write_logging(ctx: &DeviceContext, res: &mut String) {
+    let Some(logging) = &ctx.device.logging else {
+        return;
+    };
+
+    if logging.timestamps {
+        res.push_str("service timestamps log datetime msec\n");
+    }
+    if let Some(address) = ctx.app.device_address(&logging.server) {
+        writeln!(res, "logging host {address}").unwrap();
+    }
+    if let Some(level) = logging.level {
+        writeln!(res, "logging trap {}", level.keyword()).unwrap();
+    }
+    res.push('\n');
+}
+
+pub(crate) fn write_snmp(ctx: &DeviceContext, res: &mut String) {
+    let Some(snmp) = &ctx.device.snmp else {
+        return;
+    };
+
+    if let Some(location) = &snmp.location {
+        writeln!(res, "snmp-server location {location}").unwrap();
+    }
+    if let Some(contact) = &snmp.contact {
+        writeln!(res, "snmp-server contact {contact}").unwrap();
+    }
+    for community in &snmp.communities {
+        let access = match community.access {
+            SnmpAccess::Ro => "RO",
+            SnmpAccess::Rw => "RW",
+        };
+        writeln!(res, "snmp-server community {} {access}", community.name).unwrap();
+    }
+
+    // SNMPv3 users, each in a group requiring their security level
+    for user in &snmp.users {
+        let level = if user.priv_password.is_some() {
+            "priv"
+        } else {
+            "auth"
+        };
+        writeln!(res, "snmp-server group {} v3 {level}", user.group).unwrap();
+        write!(
+            res,
+            "snmp-server user {} {} v3 auth sha {}",
+            user.name, user.group, user.auth_password
+        )
+        .unwrap();
+        if let Some(priv_password) = &user.priv_password {
+            write!(res, " priv aes 128 {priv_password}").unwrap();
+        }
+        res.push('\n');
+    }
+
+    if !snmp.trap_hosts.is_empty() {
+        res.push_str("snmp-server enable traps\n");
+    }
+    for host in &snmp.trap_hosts {
+        let Some(address) = ctx.app.device_address(&host.server) else {
+            continue;
+        };
+        writeln!(
+            res,
+            "snmp-server host {address} version 2c {}",
+            host.community
+        )
+        .unwrap();
+    }
+    res.push('\n');
+}
+
+pub(crate) fn write_users(ctx: &DeviceContext, res: &mut String) {
+    for user in &ctx.device.users {
+        write!(res, "username {}", user.name).unwrap();
+        if let Some(privilege) = user.privilege {
+            write!(res, " privilege {privilege}").unwrap();
+        }
+        writeln!(res, " secret {}", user.secret).unwrap();
+    }
+    // The PPP peers authenticate with the shared password under their hostname
+    let mut ppp_peers = false;
+    for link in &ctx.links {
+        if let Some(Ppp {
+            authentication: Some(_),
+            password,
+        }) = &link.ppp
+        {
+            let far = &ctx.app.devices[link.far_key];
+            writeln!(res, "username {} password {password}", hostname(&far.name)).unwrap();
+            ppp_peers = true;
+        }
+    }
+
+    if let Some(aaa) = &ctx.device.aaa {
+        res.push_str("aaa new-model\n");
+        if let Some(list) = &aaa.login {
+            writeln!(res, "aaa authentication login {}", format_method_list(list)).unwrap();
+        }
+        if let Some(list) = &aaa.exec {
+            writeln!(res, "aaa authorization exec {}", format_method_list(list)).unwrap();
+        }
+    }
+    if !ctx.device.users.is_empty() || ppp_peers || ctx.device.aaa.is_some() {
+        res.push('\n');
+    }
+}
+
+fn format_method_list(list: &AaaMethodList) -> String {
+    let mut res = list.name.as_deref().unwrap_or("default").to_string();
+    for method in &list.methods {
+        res.push_str(match method {
+            AaaMethod::Local => " local",
+            AaaMethod::Enable => " enable",
+            AaaMethod::None => " none",
+        });
+    }
+    res
+}
+
+/// Write whether the device runs the CDP and LLDP discovery protocols
+pub(crate) fn write_discovery(ctx: &DeviceContext, res: &mut String) {
+    let device = ctx.device;
+    match device.cdp {
+        Some(true) => res.push_str("cdp run\n"),
+        Some(false) => res.push_str("no cdp run\n"),
+        None => {}
+    }
+    match device.lldp {
+        Some(true) => res.push_str("lldp run\n"),
+        Some(false) => res.push_str("no lldp run\n"),
+        None => {}
+    }
+    if device.cdp.is_some() || device.lldp.is_some() {
+        res.push('\n');
+    }
+}
+
+/// Write the discovery protocols of an interface, if they differ from the ones of the device
+pub(crate) fn write_interface(res: &mut String, link: &DirectedLink) {
+    match link.close_options.cdp {
+        Some(true) => res.push_str("   cdp enable\n"),
+        Some(false) => res.push_str("   no cdp enable\n"),
+        None => {}
+    }
+    match link.close_options.lldp {
+        Some(true) => res.push_str("   lldp transmit\n   lldp receive\n"),
+        Some(false) => res.push_str("   no lldp transmit\n   no lldp receive\n"),
+        None => {}
+    }
+}
+
+pub(crate) fn write_ssh(ctx: &DeviceContext, res: &mut String) {
+    let device = ctx.device;
+    let Some(ssh) = &device.ssh else {
+        return;
+    };
+
+    // The RSA keys are named after the hostname and domain name, which
+    // may have been set already by the device or its site
+    let site = ctx.app.site_of(ctx.key);
+    let domain_name = (device.dns.domain_name.as_ref())
+        .or_else(|| site.and_then(|site| site.domain_name.as_ref()));
+    match (&ssh.domain_name, domain_name) {
+        (Some(domain_name), _) => writeln!(res, "ip domain-name {domain_name}").unwrap(),
+        (None, Some(_)) => {}
+        (None, None) => panic!("SSH on device `{}` requires a domain name", device.name),
+    }
+    writeln!(
+        res,
+        "crypto key generate rsa general-keys modulus {}",
+        ssh.modulus
+    )
+    .unwrap();
+    res.push_str("ip ssh version 2\n");
+
+    writeln!(res, "line vty 0 {}", ssh.last_vty_line()).unwrap();
+    res.push_str("   transport input ssh\n");
+    // With AAA, the lines use the default method list
+    if device.aaa.is_none() {
+        res.push_str("   login local\n");
+    }
+    res.push_str("exit\n\n");
+}
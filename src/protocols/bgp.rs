@@ -0,0 +1,144 @@
+use std::{fmt::Write, net::IpAddr};
+
+use slotmap::DefaultKey;
+
+use crate::{App, Device, DeviceContext, DirectedLink, Direction};
+
+pub(crate) fn write_process(ctx: &DeviceContext, res: &mut String) {
+    let Some(bgp_as) = ctx.device.bgp_as else {
+        return;
+    };
+
+    // Route-maps implementing the neighbor policies
+    for policy in &ctx.device.bgp_policies {
+        writeln!(
+            res,
+            "route-map {} permit 10",
+            policy_name(ctx, policy.neighbor, policy.direction)
+        )
+        .unwrap();
+        if let Some(local_preference) = policy.local_preference {
+            writeln!(res, "   set local-preference {local_preference}").unwrap();
+        }
+        if let Some(med) = policy.med {
+            writeln!(res, "   set metric {med}").unwrap();
+        }
+        if let Some(community) = &policy.community {
+            writeln!(res, "   set community {community}").unwrap();
+        }
+        res.push_str("exit\n\n");
+    }
+
+    writeln!(res, "router bgp {bgp_as}").unwrap();
+
+    // The address of every neighbor, to apply the policies
+    let mut neighbors: Vec<(DefaultKey, IpAddr)> = vec![];
+
+    // eBGP
+    for link in &ctx.links {
+        match ctx.app.devices[link.far_key].bgp_as {
+            Some(far_as) if far_as != bgp_as => {
+                writeln!(res, "   neighbor {} remote-as {far_as}", link.far_ip.addr()).unwrap();
+                if link.bfd.is_some() {
+                    writeln!(res, "   neighbor {} fall-over bfd", link.far_ip.addr()).unwrap();
+                }
+                neighbors.push((link.far_key, link.far_ip.addr()));
+            }
+            _ => {}
+        }
+    }
+
+    // iBGP, between all the devices of the AS, unless route reflectors are used
+    let uses_rr = ctx
+        .app
+        .devices
+        .values()
+        .any(|dev| dev.bgp_as == Some(bgp_as) && !dev.bgp_rr_clients.is_empty());
+    let peers = ctx
+        .app
+        .devices
+        .iter()
+        .filter(|&(key, peer)| key != ctx.key && peer.bgp_as == Some(bgp_as));
+    for (peer_key, peer) in peers {
+        let is_client = ctx.device.bgp_rr_clients.contains(&peer_key);
+        let is_rr = |dev: &Device| !dev.bgp_rr_clients.is_empty();
+        let has_session = !uses_rr
+            || is_client
+            || peer.bgp_rr_clients.contains(&ctx.key)
+            || (is_rr(ctx.device) && is_rr(peer));
+        if !has_session {
+            continue;
+        }
+
+        // Directly connected peers use the shared link, the
+        // others are reached through their peering address
+        let peer_ip = if let Some(link) = ctx.links.iter().find(|l| l.far_key == peer_key) {
+            let peer_ip = link.far_ip.addr();
+            writeln!(res, "   neighbor {peer_ip} remote-as {bgp_as}").unwrap();
+            peer_ip
+        } else if let (Some(own), Some(peer)) = (
+            ibgp_peering_link(ctx.app, ctx.key),
+            ibgp_peering_link(ctx.app, peer_key),
+        ) {
+            let peer_ip = peer.close_ip.addr();
+            writeln!(res, "   neighbor {peer_ip} remote-as {bgp_as}").unwrap();
+            writeln!(
+                res,
+                "   neighbor {peer_ip} update-source {}",
+                own.iface_name(),
+            )
+            .unwrap();
+            peer_ip
+        } else {
+            continue;
+        };
+        if is_client {
+            writeln!(res, "   neighbor {peer_ip} route-reflector-client").unwrap();
+        }
+        neighbors.push((peer_key, peer_ip));
+    }
+
+    for policy in &ctx.device.bgp_policies {
+        let Some(&(_, peer_ip)) = neighbors.iter().find(|(key, _)| *key == policy.neighbor) else {
+            continue;
+        };
+        let direction = policy.direction.keyword();
+        writeln!(
+            res,
+            "   neighbor {peer_ip} route-map {} {direction}",
+            policy_name(ctx, policy.neighbor, policy.direction),
+        )
+        .unwrap();
+        if policy.community.is_some() {
+            writeln!(res, "   neighbor {peer_ip} send-community").unwrap();
+        }
+    }
+
+    for link in &ctx.links {
+        if link.bgp {
+            writeln!(
+                res,
+                "   network {} mask {}",
+                link.far_ip.network(),
+                link.far_ip.netmask(),
+            )
+            .unwrap();
+        }
+    }
+    res.push_str("exit\n\n");
+}
+
+/// The link whose address a device uses to establish iBGP sessions
+/// with peers it is not directly connected to
+fn ibgp_peering_link(app: &App, key: DefaultKey) -> Option<DirectedLink> {
+    app.links_of(key).into_iter().next()
+}
+
+/// The name of the route-map applied to a neighbor
+fn policy_name(ctx: &DeviceContext, neighbor: DefaultKey, direction: Direction) -> String {
+    let direction = match direction {
+        Direction::In => "IN",
+        Direction::Out => "OUT",
+    };
+    format!("BGP_{}_{direction}", ctx.app.devices[neighbor].name)
+}
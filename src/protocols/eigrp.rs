@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use crate::{DeviceContext, DirectedLink, DistributeList, FilteredProtocol, SummaryProtocol};
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    let Some(eigrp_as) = ctx.device.eigrp_as else {
+        return;
+    };
+
+    for summary in &ctx.device.summaries {
+        if summary.protocol
+            != (SummaryProtocol::Eigrp {
+                towards: link.far_key,
+            })
+        {
+            continue;
+        }
+        let subnets = ctx
+            .links
+            .iter()
+            .filter(|other| other.eigrp && other.far_key != link.far_key)
+            .map(|other| other.far_ip);
+        if let Some(prefix) = summary.prefix(subnets) {
+            writeln!(
+                res,
+                "   ip summary-address eigrp {eigrp_as} {} {}",
+                prefix.network(),
+                prefix.netmask(),
+            )
+            .unwrap();
+        }
+    }
+}
+
+pub(crate) fn write_process(ctx: &DeviceContext, res: &mut String) {
+    let Some(eigrp_as) = ctx.device.eigrp_as else {
+        return;
+    };
+
+    writeln!(res, "router eigrp {eigrp_as}").unwrap();
+    if let Some(distance) = ctx.device.distances.eigrp {
+        writeln!(res, "   distance {distance}").unwrap();
+    }
+    if let Some(k) = ctx.device.eigrp_k_values {
+        writeln!(
+            res,
+            "   metric weights 0 {} {} {} {} {}",
+            k.k1, k.k2, k.k3, k.k4, k.k5,
+        )
+        .unwrap();
+    }
+    if let Some(variance) = ctx.device.eigrp_variance {
+        writeln!(res, "   variance {variance}").unwrap();
+    }
+    DistributeList::write_all(res, ctx.device, FilteredProtocol::Eigrp);
+    for link in &ctx.links {
+        if link.eigrp && link.bfd.is_some() {
+            writeln!(res, "   bfd interface {}", link.iface_name()).unwrap();
+        }
+    }
+    write_networks(res, &ctx.links, "   ");
+    if ctx.links.iter().any(|link| link.eigrp) {
+        for loopback in ctx.device.loopbacks.iter().filter(|ip| ip.addr().is_ipv4()) {
+            writeln!(
+                res,
+                "   network {} {}",
+                loopback.network(),
+                loopback.hostmask(),
+            )
+            .unwrap();
+        }
+    }
+    for vrf in &ctx.device.vrfs {
+        let vrf_ctx = ctx.in_vrf(Some(&vrf.name));
+        if vrf_ctx.links.iter().any(|link| link.eigrp) {
+            writeln!(
+                res,
+                "   address-family ipv4 vrf {} autonomous-system {eigrp_as}",
+                vrf.name
+            )
+            .unwrap();
+            write_networks(res, &vrf_ctx.links, "      ");
+            res.push_str("   exit-address-family\n");
+        }
+    }
+    res.push_str("exit\n\n");
+}
+
+fn write_networks(res: &mut String, links: &[DirectedLink], indent: &str) {
+    for link in links {
+        if link.eigrp && link.close_options.unnumbered.is_none() {
+            writeln!(
+                res,
+                "{indent}network {} {}",
+                link.close_ip.network(),
+                link.close_ip.hostmask(),
+            )
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::fmt::Write;
+
+use crate::{DeviceContext, DirectedLink, IsisLevel};
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    let (Some(_), Some(level)) = (&ctx.device.isis_net, link.isis) else {
+        return;
+    };
+
+    if link.has_ipv4() {
+        res.push_str("   ip router isis\n");
+    }
+    if link.has_ipv6() {
+        res.push_str("   ipv6 router isis\n");
+    }
+    let circuit_type = match level {
+        IsisLevel::Level1 => "level-1",
+        IsisLevel::Level2 => "level-2-only",
+        IsisLevel::Level1And2 => "level-1-2",
+    };
+    writeln!(res, "   isis circuit-type {circuit_type}").unwrap();
+}
+
+pub(crate) fn write_process(ctx: &DeviceContext, res: &mut String) {
+    let Some(net) = &ctx.device.isis_net else {
+        return;
+    };
+
+    writeln!(res, "router isis\n   net {net}\nexit\n").unwrap();
+}
@@ -0,0 +1,11 @@
+//! The routing protocols supported by the generator
+//!
+//! Each protocol has a `write_interface` function, for the commands that go
+//! under every interface, and a `write_process` function, for its global
+//! configuration. Both are only called by [`crate::App::to_commands`]
+
+pub(crate) mod bgp;
+pub(crate) mod eigrp;
+pub(crate) mod isis;
+pub(crate) mod ospf;
+pub(crate) mod rip;
@@ -0,0 +1,220 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use ipnet::IpNet;
+
+use crate::{
+    AreaType, DeviceContext, DirectedLink, DistributeList, FilteredProtocol, OspfNetworkType,
+    SummaryProtocol,
+};
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    if let Some(cost) = link.ospf_cost {
+        writeln!(res, "   ip ospf cost {cost}").unwrap();
+    }
+    if let (Some(_), Some(network_type)) = (link.ospf_area, link.ospf_network_type) {
+        let network_type = match network_type {
+            OspfNetworkType::PointToPoint => "point-to-point",
+            OspfNetworkType::Broadcast => "broadcast",
+            OspfNetworkType::NonBroadcast => "non-broadcast",
+        };
+        if link.has_ipv4() {
+            writeln!(res, "   ip ospf network {network_type}").unwrap();
+        }
+        if link.has_ipv6() {
+            writeln!(res, "   ipv6 ospf network {network_type}").unwrap();
+        }
+    }
+    if link.ospf_area.is_some() && link.bfd.is_some() {
+        res.push_str("   ip ospf bfd\n");
+    }
+    if let (Some(ospf_area), true) = (link.ospf_area, link.has_ipv6()) {
+        let process = link.ospf_process.unwrap_or(1);
+        writeln!(res, "   ipv6 ospf {process} area {ospf_area}").unwrap();
+    }
+    let area_auth = link.has_ipv4()
+        && matches!(link.ospf_area, Some(a) if ctx.app.ospf_auth_areas.contains(&a));
+    if link.ospf_auth && !area_auth {
+        res.push_str("   ip ospf authentication message-digest\n");
+    }
+    if link.ospf_auth || area_auth {
+        let key = ctx
+            .app
+            .ospf_md5_key
+            .as_ref()
+            .expect("OSPF authentication requires a key");
+        writeln!(res, "   ip ospf message-digest-key 1 md5 {key}").unwrap();
+    }
+}
+
+pub(crate) fn write_process(ctx: &DeviceContext, res: &mut String) {
+    let device = ctx.device;
+
+    // OSPF, with process 1 always present
+    let mut processes = links_by_process(ctx, 1, DirectedLink::has_ipv4);
+    processes.entry(1).or_default();
+    for (&process, links) in &processes {
+        writeln!(res, "router ospf {process}").unwrap();
+        if process == 1 {
+            if let Some(router_id) = device.ospf_router_id {
+                writeln!(res, "   router-id {router_id}").unwrap();
+            }
+            if let Some(distance) = device.distances.ospf {
+                writeln!(res, "   distance {distance}").unwrap();
+            }
+            let redistributions = &device.redistributions;
+            if let Some(redistribution) = &redistributions.ospf_to_rip {
+                redistribution.write(res, "rip", true);
+            }
+            if let Some(redistribution) = &redistributions.ospf_connected {
+                redistribution.write(res, "connected", true);
+            }
+            if let Some(redistribution) = &redistributions.ospf_static {
+                redistribution.write(res, "static", true);
+            }
+        }
+        write_mutual_redistribution(ctx, res, &processes, process, " subnets");
+        if process == 1 {
+            DistributeList::write_all(res, device, FilteredProtocol::Ospf);
+            if device.originate_default_route {
+                res.push_str("   default-information originate\n");
+            }
+        }
+        let areas: BTreeSet<u16> = links.iter().filter_map(|link| link.ospf_area).collect();
+        write_area_types(ctx, res, &areas);
+        for area in areas.intersection(&ctx.app.ospf_auth_areas) {
+            writeln!(res, "   area {area} authentication message-digest").unwrap();
+        }
+        for summary in &ctx.device.summaries {
+            let SummaryProtocol::Ospf { area } = summary.protocol else {
+                continue;
+            };
+            if !areas.contains(&area) {
+                continue;
+            }
+            let subnets = links
+                .iter()
+                .filter(|link| link.ospf_area == Some(area))
+                .map(|link| link.far_ip);
+            if let Some(IpNet::V4(prefix)) = summary.prefix(subnets) {
+                writeln!(
+                    res,
+                    "   area {area} range {} {}",
+                    prefix.network(),
+                    prefix.netmask(),
+                )
+                .unwrap();
+            }
+        }
+        write_networks(res, links);
+        // Loopbacks belong to the lowest area of the default process
+        if let (1, Some(area)) = (process, areas.first()) {
+            for loopback in device.loopbacks.iter().filter(|ip| ip.addr().is_ipv4()) {
+                writeln!(
+                    res,
+                    "   network {} {} area {area}",
+                    loopback.network(),
+                    loopback.hostmask(),
+                )
+                .unwrap();
+            }
+        }
+        res.push_str("exit\n\n");
+    }
+
+    // The processes of every VRF
+    for (i, vrf) in device.vrfs.iter().enumerate() {
+        let vrf_ctx = ctx.in_vrf(Some(&vrf.name));
+        let default_process = i as u16 + 2;
+        for (process, links) in links_by_process(&vrf_ctx, default_process, DirectedLink::has_ipv4)
+        {
+            writeln!(res, "router ospf {process} vrf {}", vrf.name).unwrap();
+            write_networks(res, &links);
+            res.push_str("exit\n\n");
+        }
+    }
+
+    // OSPFv3
+    let processes_v6 = links_by_process(ctx, 1, DirectedLink::has_ipv6);
+    for (&process, links) in &processes_v6 {
+        writeln!(res, "ipv6 router ospf {process}").unwrap();
+        if let (1, Some(router_id)) = (process, device.ospf_router_id) {
+            writeln!(res, "   router-id {router_id}").unwrap();
+        }
+        write_mutual_redistribution(ctx, res, &processes_v6, process, "");
+        let areas: BTreeSet<u16> = links.iter().filter_map(|link| link.ospf_area).collect();
+        write_area_types(ctx, res, &areas);
+        res.push_str("exit\n\n");
+    }
+}
+
+/// Group the links running OSPF by the process they belong to, keeping
+/// only the ones whose address family matches `family`
+fn links_by_process<'a>(
+    ctx: &'a DeviceContext,
+    default_process: u16,
+    family: impl Fn(&DirectedLink) -> bool,
+) -> BTreeMap<u16, Vec<&'a DirectedLink>> {
+    let mut processes: BTreeMap<u16, Vec<&DirectedLink>> = BTreeMap::new();
+    for link in &ctx.links {
+        if link.ospf_area.is_some() && family(link) {
+            let process = link.ospf_process.unwrap_or(default_process);
+            processes.entry(process).or_default().push(link);
+        }
+    }
+    processes
+}
+
+fn write_mutual_redistribution<T>(
+    ctx: &DeviceContext,
+    res: &mut String,
+    processes: &BTreeMap<u16, T>,
+    process: u16,
+    suffix: &str,
+) {
+    if !ctx.device.ospf_mutual_redistribution {
+        return;
+    }
+    for &other in processes.keys().filter(|&&other| other != process) {
+        writeln!(res, "   redistribute ospf {other}{suffix}").unwrap();
+    }
+}
+
+/// Write the type of each of the given OSPF areas, as seen by a
+/// router which participates in all of them
+fn write_area_types(ctx: &DeviceContext, res: &mut String, areas: &BTreeSet<u16>) {
+    let is_abr = areas.contains(&0) && areas.len() > 1;
+    for area in areas {
+        // `no-summary` only has effect on the ABRs
+        let cmd = match ctx.app.ospf_areas.get(area) {
+            None => continue,
+            Some(AreaType::Stub) => "stub",
+            Some(AreaType::TotallyStubby) if is_abr => "stub no-summary",
+            Some(AreaType::TotallyStubby) => "stub",
+            Some(AreaType::Nssa) => "nssa",
+            Some(AreaType::TotallyNssa) if is_abr => "nssa no-summary",
+            Some(AreaType::TotallyNssa) => "nssa",
+        };
+        writeln!(res, "   area {area} {cmd}").unwrap();
+    }
+}
+
+fn write_networks(res: &mut String, links: &[&DirectedLink]) {
+    for link in links {
+        if link.close_options.unnumbered.is_some() {
+            continue;
+        }
+        if let (Some(ospf_area), IpNet::V4(_)) = (link.ospf_area, link.close_ip) {
+            writeln!(
+                res,
+                "   network {} {} area {}",
+                link.close_ip.network(),
+                link.close_ip.hostmask(),
+                ospf_area,
+            )
+            .unwrap();
+        }
+    }
+}
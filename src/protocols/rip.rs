@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use crate::{
+    DeviceContext, DirectedLink, DistributeList, FilteredProtocol, Redistribution, SummaryProtocol,
+};
+
+/// The name of the RIPng process of every device
+const RIPNG_PROCESS: &str = "RIPNG";
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    if let Some(chain) = &link.close_options.rip_key_chain {
+        writeln!(
+            res,
+            "   ip rip authentication mode md5\n   ip rip authentication key-chain {chain}"
+        )
+        .unwrap();
+    }
+    if link.has_ipv6() && reaches_rip(ctx, link) {
+        writeln!(res, "   ipv6 rip {RIPNG_PROCESS} enable").unwrap();
+    }
+    for summary in &ctx.device.summaries {
+        if summary.protocol
+            != (SummaryProtocol::Rip {
+                towards: link.far_key,
+            })
+        {
+            continue;
+        }
+        let subnets = ctx
+            .links
+            .iter()
+            .filter(|other| {
+                other.far_key != link.far_key && ctx.app.rip_enabled.contains(&other.far_key)
+            })
+            .map(|other| other.far_ip);
+        if let Some(prefix) = summary.prefix(subnets) {
+            writeln!(
+                res,
+                "   ip summary-address rip {} {}",
+                prefix.network(),
+                prefix.netmask(),
+            )
+            .unwrap();
+        }
+    }
+}
+
+pub(crate) fn write_process(ctx: &DeviceContext, res: &mut String) {
+    let device = ctx.device;
+
+    // RIP v2
+    writeln!(res, "router rip\n   version {}", device.rip.version as u8).unwrap();
+    if !device.rip.auto_summary {
+        res.push_str("   no auto-summary\n");
+    }
+    if let Some(distance) = device.distances.rip {
+        writeln!(res, "   distance {distance}").unwrap();
+    }
+    for link in &ctx.links {
+        if link.close_options.rip_passive {
+            writeln!(res, "   passive-interface {}", link.iface_name()).unwrap();
+        }
+    }
+    let redistributions = &device.redistributions;
+    if let Some(redistribution) = &redistributions.rip_to_ospf {
+        // RIP needs a seed metric to advertise the routes
+        let redistribution = Redistribution {
+            metric: Some(redistribution.metric.unwrap_or(1)),
+            ..redistribution.clone()
+        };
+        redistribution.write(res, "ospf 1", false);
+    }
+    if let Some(redistribution) = &redistributions.rip_connected {
+        redistribution.write(res, "connected", false);
+    }
+    if let Some(redistribution) = &redistributions.rip_static {
+        redistribution.write(res, "static", false);
+    }
+    DistributeList::write_all(res, device, FilteredProtocol::Rip);
+    if device.originate_default_route {
+        res.push_str("   default-information originate\n");
+    }
+    write_networks(ctx, res, "   ");
+    write_loopbacks(ctx, res);
+    for vrf in &device.vrfs {
+        let vrf_ctx = ctx.in_vrf(Some(&vrf.name));
+        if vrf_ctx.links.iter().any(|link| is_rip_v4(ctx, link)) {
+            writeln!(res, "   address-family ipv4 vrf {}", vrf.name).unwrap();
+            write_networks(&vrf_ctx, res, "      ");
+            res.push_str("   exit-address-family\n");
+        }
+    }
+    res.push_str("exit\n\n");
+
+    // RIPng
+    if ctx
+        .links
+        .iter()
+        .any(|link| link.has_ipv6() && reaches_rip(ctx, link))
+    {
+        writeln!(res, "ipv6 router rip {RIPNG_PROCESS}\nexit\n").unwrap();
+    }
+}
+
+fn write_networks(ctx: &DeviceContext, res: &mut String, indent: &str) {
+    for link in &ctx.links {
+        // Unnumbered interfaces are covered by the network of their loopback
+        if is_rip_v4(ctx, link) && link.close_options.unnumbered.is_none() {
+            writeln!(res, "{indent}network {}", link.close_ip.network()).unwrap();
+        }
+    }
+}
+
+fn write_loopbacks(ctx: &DeviceContext, res: &mut String) {
+    if !ctx.links.iter().any(|link| is_rip_v4(ctx, link)) {
+        return;
+    }
+    for loopback in ctx.device.loopbacks.iter().filter(|ip| ip.addr().is_ipv4()) {
+        writeln!(res, "   network {}", loopback.network()).unwrap();
+    }
+}
+
+fn is_rip_v4(ctx: &DeviceContext, link: &DirectedLink) -> bool {
+    link.far_ip.addr().is_ipv4() && reaches_rip(ctx, link)
+}
+
+/// Whether RIP runs on a device reached through the link
+fn reaches_rip(ctx: &DeviceContext, link: &DirectedLink) -> bool {
+    let rip_enabled = &ctx.app.rip_enabled;
+    link.far_routers.iter().any(|far| rip_enabled.contains(far))
+}
@@ -0,0 +1,57 @@
+//! Quality of service: the classes of traffic, and how the interfaces
+//! treat each of them
+
+use std::fmt::Write;
+
+use crate::{DeviceContext, DirectedLink};
+
+pub(crate) fn write_policies(ctx: &DeviceContext, res: &mut String) {
+    let device = ctx.device;
+
+    for class_map in &device.class_maps {
+        let kind = if class_map.match_any {
+            "match-any"
+        } else {
+            "match-all"
+        };
+        writeln!(res, "class-map {kind} {}", class_map.name).unwrap();
+        for clause in &class_map.matches {
+            writeln!(res, "   match {clause}").unwrap();
+        }
+        res.push_str("exit\n");
+    }
+
+    for policy_map in &device.policy_maps {
+        writeln!(res, "policy-map {}", policy_map.name).unwrap();
+        for class in &policy_map.classes {
+            writeln!(res, "   class {}", class.class).unwrap();
+            if let Some(kbps) = class.priority {
+                writeln!(res, "      priority {kbps}").unwrap();
+            }
+            if let Some(kbps) = class.bandwidth {
+                writeln!(res, "      bandwidth {kbps}").unwrap();
+            }
+            if let Some(bps) = class.police {
+                writeln!(res, "      police {bps}").unwrap();
+            }
+            if let Some(dscp) = &class.set_dscp {
+                writeln!(res, "      set dscp {dscp}").unwrap();
+            }
+            res.push_str("   exit\n");
+        }
+        res.push_str("exit\n");
+    }
+
+    if !device.class_maps.is_empty() || !device.policy_maps.is_empty() {
+        res.push('\n');
+    }
+}
+
+pub(crate) fn write_interface(res: &mut String, link: &DirectedLink) {
+    if let Some(policy) = &link.close_options.service_policy_in {
+        writeln!(res, "   service-policy input {policy}").unwrap();
+    }
+    if let Some(policy) = &link.close_options.service_policy_out {
+        writeln!(res, "   service-policy output {policy}").unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+//! Generation of random topologies, for practice labs
+
+use std::collections::BTreeSet;
+
+use crate::{App, RandomTopology, RoutingProtocol};
+
+/// A SplitMix64 generator: not suited for cryptography, but small and
+/// stable, so that a seed always gives the same topology
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn choose<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        (!items.is_empty()).then(|| items[self.below(items.len())])
+    }
+}
+
+pub(crate) fn generate(topology: &RandomTopology) -> App {
+    let RandomTopology {
+        seed,
+        devices: n,
+        min_degree,
+        max_degree,
+        ..
+    } = *topology;
+    assert!(n >= 2, "A topology needs at least 2 routers");
+    assert!(
+        max_degree >= 2 && min_degree <= max_degree,
+        "The maximum degree must be at least 2, and the minimum at most the maximum"
+    );
+
+    let mut rng = Rng(seed);
+    let mut app = App::new();
+    app.set_address_pool("10.0.0.0/16", 30);
+    let routers = app.template_routers("R", n);
+
+    let mut links = BTreeSet::new();
+    let mut degrees = vec![0; n];
+
+    // A spanning tree first, so that every router is reachable
+    for i in 1..n {
+        let candidates: Vec<_> = (0..i).filter(|&j| degrees[j] < max_degree).collect();
+        let j = rng.choose(&candidates).expect("A tree always has a leaf");
+        connect(&mut links, &mut degrees, i, j);
+    }
+
+    // Then more links, until each router reaches the degree it drew
+    let targets: Vec<_> = (0..n)
+        .map(|_| min_degree + rng.below(max_degree - min_degree + 1))
+        .collect();
+    for a in 0..n {
+        while degrees[a] < targets[a] {
+            let candidates: Vec<_> = (0..n)
+                .filter(|&b| b != a && degrees[b] < max_degree)
+                .filter(|&b| !links.contains(&(a.min(b), a.max(b))))
+                .collect();
+            let Some(b) = rng.choose(&candidates) else {
+                break;
+            };
+            connect(&mut links, &mut degrees, a, b);
+        }
+    }
+
+    let protocol = choose_protocol(&mut rng, &topology.protocols);
+    if protocol == RoutingProtocol::Rip {
+        app.rip_enabled.extend(&routers);
+    }
+    if protocol == RoutingProtocol::Eigrp {
+        for &key in &routers {
+            app.devices[key].eigrp_as = Some(1);
+        }
+    }
+    for (a, b) in links {
+        let (r1, r2) = (routers[a], routers[b]);
+        let subnet = app.allocate_subnet(r1, r2).to_string();
+        let ospf_area = (protocol == RoutingProtocol::Ospf).then_some(0);
+        let link = app.link(r1, r2, &subnet, ospf_area);
+        link.eigrp = protocol == RoutingProtocol::Eigrp;
+    }
+    app
+}
+
+fn connect(links: &mut BTreeSet<(usize, usize)>, degrees: &mut [usize], a: usize, b: usize) {
+    links.insert((a.min(b), a.max(b)));
+    degrees[a] += 1;
+    degrees[b] += 1;
+}
+
+/// Draw one of the protocols, each with a probability proportional to its weight
+fn choose_protocol(rng: &mut Rng, protocols: &[(RoutingProtocol, u32)]) -> RoutingProtocol {
+    let total: u32 = protocols.iter().map(|&(_, weight)| weight).sum();
+    assert!(total > 0, "At least one protocol must have a weight");
+    let mut drawn = rng.below(total as usize) as u32;
+    for &(protocol, weight) in protocols {
+        if drawn < weight {
+            return protocol;
+        }
+        drawn -= weight;
+    }
+    unreachable!()
+}
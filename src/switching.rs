@@ -0,0 +1,145 @@
+//! Layer 2 configuration of the ports of switches
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Write,
+};
+
+use slotmap::DefaultKey;
+
+use crate::{
+    management, write_address, write_shutdown, App, DeviceContext, DeviceKind, DirectedLink,
+    PortSecurity, PortSecurityViolation,
+};
+
+/// Whether the close side of the link is a switched port rather than a routed one
+pub(crate) fn is_switchport(ctx: &DeviceContext, link: &DirectedLink) -> bool {
+    match ctx.device.kind {
+        DeviceKind::Switch => true,
+        DeviceKind::MultilayerSwitch => !link.close_options.routed,
+        _ => false,
+    }
+}
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    let Some(group) = link.channel_group else {
+        writeln!(res, "interface {}", link.iface_name()).unwrap();
+        ctx.write_description(res, link, link.far_iface);
+        write_switchport(ctx, res, link);
+        management::write_interface(res, link);
+        write_shutdown(res, link);
+        res.push_str("exit\n\n");
+        return;
+    };
+
+    // The members inherit the switchport settings of the port-channel
+    let close_members = std::iter::once(link.close_iface).chain(link.close_members.iter().copied());
+    let far_members = std::iter::once(link.far_iface).chain(link.far_members.iter().copied());
+    for (iface, far_iface) in close_members.zip(far_members) {
+        let port_type = link.close_options.port_type;
+        let name = ctx
+            .app
+            .port_name(ctx.key, link.interface_type, port_type, iface);
+        writeln!(res, "interface {name}").unwrap();
+        ctx.write_description(res, link, far_iface);
+        writeln!(res, "   channel-group {group} mode active").unwrap();
+        management::write_interface(res, link);
+        write_shutdown(res, link);
+        res.push_str("exit\n\n");
+    }
+    writeln!(res, "interface Port-channel {group}").unwrap();
+    write_switchport(ctx, res, link);
+    res.push_str("exit\n\n");
+}
+
+/// Write the VLAN interfaces, which route between the VLANs of multilayer switches
+pub(crate) fn write_svis(ctx: &DeviceContext, res: &mut String) {
+    for svi in &ctx.device.svis {
+        writeln!(res, "interface Vlan{}", svi.vlan).unwrap();
+        write_address(res, svi.ip);
+        res.push_str("   no shutdown\nexit\n\n");
+    }
+}
+
+fn write_switchport(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    let Some(trunk) = &link.trunk else {
+        res.push_str("   switchport mode access\n");
+        if let Some(vlan) = link.access_vlan {
+            writeln!(res, "   switchport access vlan {vlan}").unwrap();
+        }
+        if let Some(vlan) = link.voice_vlan {
+            writeln!(res, "   switchport voice vlan {vlan}").unwrap();
+        }
+        if let Some(security) = link.close_options.port_security {
+            write_port_security(res, security);
+        }
+        write_dhcp_snooping_trust(ctx, res, link);
+        return;
+    };
+
+    // Multilayer switches also support ISL, so the encapsulation must be chosen first
+    if ctx.device.kind == DeviceKind::MultilayerSwitch {
+        res.push_str("   switchport trunk encapsulation dot1q\n");
+    }
+    res.push_str("   switchport mode trunk\n");
+    if let Some(native_vlan) = trunk.native_vlan {
+        writeln!(res, "   switchport trunk native vlan {native_vlan}").unwrap();
+    }
+    if !trunk.allowed_vlans.is_empty() {
+        let vlans: Vec<_> = trunk.allowed_vlans.iter().map(u16::to_string).collect();
+        writeln!(res, "   switchport trunk allowed vlan {}", vlans.join(",")).unwrap();
+    }
+    write_dhcp_snooping_trust(ctx, res, link);
+}
+
+fn write_dhcp_snooping_trust(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    let Some(snooping) = &ctx.device.dhcp_snooping else {
+        return;
+    };
+    if leads_to(ctx.app, ctx.key, link.far_key, snooping.server) {
+        res.push_str("   ip dhcp snooping trust\n");
+    }
+}
+
+/// Whether `target` can be reached from `start` without passing through `avoid`
+fn leads_to(app: &App, avoid: DefaultKey, start: DefaultKey, target: DefaultKey) -> bool {
+    let mut visited = HashSet::from([avoid, start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(key) = queue.pop_front() {
+        if key == target {
+            return true;
+        }
+        for &(r1, r2) in app.adjacency.keys() {
+            let next = if r1 == key {
+                r2
+            } else if r2 == key {
+                r1
+            } else {
+                continue;
+            };
+            if visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+fn write_port_security(res: &mut String, security: PortSecurity) {
+    res.push_str("   switchport port-security\n");
+    if let Some(maximum) = security.maximum {
+        writeln!(res, "   switchport port-security maximum {maximum}").unwrap();
+    }
+    let violation = match security.violation {
+        Some(PortSecurityViolation::Protect) => Some("protect"),
+        Some(PortSecurityViolation::Restrict) => Some("restrict"),
+        Some(PortSecurityViolation::Shutdown) => Some("shutdown"),
+        None => None,
+    };
+    if let Some(violation) = violation {
+        writeln!(res, "   switchport port-security violation {violation}").unwrap();
+    }
+    if security.sticky {
+        res.push_str("   switchport port-security mac-address sticky\n");
+    }
+}
@@ -0,0 +1,399 @@
+//! A parser for the subset of TOML used by topology files, which turns
+//! them into the same values as the equivalent YAML
+//!
+//! Dates and times aren't supported, since no setting uses them
+
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+
+use crate::document_key;
+
+/// Deserialize a TOML document, reporting the line of the first syntax error
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    let value = Parser::new(text).document()?;
+    serde_yaml::from_value(value).map_err(|e| e.to_string())
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Whether the text continues with `s`
+    fn peek_str(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {message}", self.line)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{c}`")))
+        }
+    }
+
+    /// Skip the spaces, and the comment until the end of the line
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.next();
+            }
+        }
+    }
+
+    /// Skip the spaces, the comments and the blank lines
+    fn skip_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            if !matches!(self.peek(), Some('\n' | '\r')) {
+                return;
+            }
+            self.next();
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        if self.peek() == Some('\r') {
+            self.next();
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some(_) => Err(self.error("expected the end of the line")),
+        }
+    }
+
+    fn document(&mut self) -> Result<Value, String> {
+        let mut root = Mapping::new();
+        // The path of the table the keys are added to
+        let mut table = vec![];
+        loop {
+            self.skip_lines();
+            match self.peek() {
+                None => return Ok(Value::Mapping(root)),
+                Some('[') => {
+                    self.next();
+                    let array = self.peek() == Some('[');
+                    if array {
+                        self.next();
+                    }
+                    self.skip_spaces();
+                    table = self.key()?;
+                    self.skip_spaces();
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                        let (last, parent) = table.split_last().unwrap();
+                        let parent = self.table_at(&mut root, parent)?;
+                        let last = document_key(last.clone());
+                        if !parent.contains_key(&last) {
+                            parent.insert(last.clone(), Value::Sequence(vec![]));
+                        }
+                        match parent.get_mut(&last) {
+                            Some(Value::Sequence(tables)) => {
+                                tables.push(Value::Mapping(Mapping::new()));
+                            }
+                            _ => return Err(self.error("the key isn't an array of tables")),
+                        }
+                    } else {
+                        self.table_at(&mut root, &table)?;
+                    }
+                }
+                Some(_) => {
+                    let (key, value) = self.key_value()?;
+                    let table = self.table_at(&mut root, &table)?;
+                    self.insert(table, &key, value)?;
+                }
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    /// The table at `path`, which is created if missing. The path goes
+    /// through the last table of the arrays of tables
+    fn table_at<'m>(
+        &self,
+        mut table: &'m mut Mapping,
+        path: &[String],
+    ) -> Result<&'m mut Mapping, String> {
+        for key in path {
+            let key = document_key(key.clone());
+            if !table.contains_key(&key) {
+                table.insert(key.clone(), Value::Mapping(Mapping::new()));
+            }
+            table = match table.get_mut(&key) {
+                Some(Value::Mapping(table)) => table,
+                Some(Value::Sequence(tables)) => match tables.last_mut() {
+                    Some(Value::Mapping(table)) => table,
+                    _ => return Err(self.error("the key isn't a table")),
+                },
+                _ => return Err(self.error("the key isn't a table")),
+            };
+        }
+        Ok(table)
+    }
+
+    /// Insert a value at a dotted key, which mustn't be defined already
+    fn insert(&self, table: &mut Mapping, key: &[String], value: Value) -> Result<(), String> {
+        let (last, parent) = key.split_last().unwrap();
+        let table = self.table_at(table, parent)?;
+        let last = document_key(last.clone());
+        if table.contains_key(&last) {
+            let key = key.join(".");
+            return Err(self.error(&format!("the key `{key}` is defined twice")));
+        }
+        table.insert(last, value);
+        Ok(())
+    }
+
+    fn key_value(&mut self) -> Result<(Vec<String>, Value), String> {
+        let key = self.key()?;
+        self.skip_spaces();
+        self.expect('=')?;
+        self.skip_spaces();
+        let value = self.value()?;
+        Ok((key, value))
+    }
+
+    /// A dotted key, made of bare or quoted parts
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut parts = vec![];
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c @ ('A'..='Z' | 'a'..='z' | '0'..='9' | '_' | '-')) =
+                        self.peek()
+                    {
+                        part.push(c);
+                        self.next();
+                    }
+                    if part.is_empty() {
+                        return Err(self.error("expected a key"));
+                    }
+                    part
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.next();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') if self.peek_str("\"\"\"") => self.multiline_string().map(Value::String),
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') if self.peek_str("'''") => self.multiline_literal().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            _ if self.peek_str("true") => {
+                self.pos += 4;
+                Ok(Value::Bool(true))
+            }
+            _ if self.peek_str("false") => {
+                self.pos += 5;
+                Ok(Value::Bool(false))
+            }
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let mut number = String::new();
+        while let Some(c @ ('0'..='9' | '+' | '-' | '.' | '_' | 'e' | 'E')) = self.peek() {
+            if c != '_' {
+                number.push(c);
+            }
+            self.next();
+        }
+        if let Ok(number) = number.parse::<i64>() {
+            Ok(Value::Number(number.into()))
+        } else if let Ok(number) = number.parse::<f64>() {
+            Ok(Value::Number(number.into()))
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut res = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(res),
+                Some('\\') => res.push(self.escape()?),
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => res.push(c),
+            }
+        }
+    }
+
+    /// A basic string spanning more lines, whose first newline is dropped
+    fn multiline_string(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        if self.peek_str("\r\n") {
+            self.next();
+        }
+        if self.peek() == Some('\n') {
+            self.next();
+        }
+        let mut res = String::new();
+        while !self.peek_str("\"\"\"") {
+            match self.next() {
+                // A backslash at the end of a line joins it with the next one
+                Some('\\') if matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) => {
+                    while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                        self.next();
+                    }
+                }
+                Some('\\') => res.push(self.escape()?),
+                Some(c) => res.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        self.pos += 3;
+        Ok(res)
+    }
+
+    /// The character escaped by a backslash
+    fn escape(&mut self) -> Result<char, String> {
+        let c = match self.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(c @ ('u' | 'U')) => {
+                let len = if c == 'u' { 4 } else { 8 };
+                let mut code = String::new();
+                for _ in 0..len {
+                    code.extend(self.next());
+                }
+                u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error(&format!("invalid unicode escape `{code}`")))?
+            }
+            _ => return Err(self.error("invalid escape sequence")),
+        };
+        Ok(c)
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut res = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(res),
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => res.push(c),
+            }
+        }
+    }
+
+    fn multiline_literal(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        if self.peek_str("\r\n") {
+            self.next();
+        }
+        if self.peek() == Some('\n') {
+            self.next();
+        }
+        let mut res = String::new();
+        while !self.peek_str("'''") {
+            res.push(
+                self.next()
+                    .ok_or_else(|| self.error("unterminated string"))?,
+            );
+        }
+        self.pos += 3;
+        Ok(res)
+    }
+
+    /// An array, whose values can span more lines
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        loop {
+            self.skip_lines();
+            if self.peek() == Some(']') {
+                self.next();
+                return Ok(Value::Sequence(values));
+            }
+            values.push(self.value()?);
+            self.skip_lines();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Sequence(values)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    /// A table written on a single line
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut table = Mapping::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Value::Mapping(table));
+        }
+        loop {
+            self.skip_spaces();
+            let (key, value) = self.key_value()?;
+            self.insert(&mut table, &key, value)?;
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Mapping(table)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Site-to-site IPsec VPNs, which encrypt the traffic between the
+//! networks behind two edge routers
+
+use std::fmt::Write;
+
+use crate::{DeviceContext, DirectedLink, Vpn, VpnPeer};
+
+const CRYPTO_MAP: &str = "VPN-MAP";
+const TRANSFORM_SET: &str = "VPN-SET";
+
+/// The VPNs of the device, with its own side and the other one
+fn vpns<'a>(ctx: &'a DeviceContext) -> impl Iterator<Item = (&'a Vpn, &'a VpnPeer, &'a VpnPeer)> {
+    ctx.app.vpns.iter().filter_map(|vpn| match &vpn.peers {
+        [own, other] | [other, own] if own.router == ctx.key => Some((vpn, own, other)),
+        _ => None,
+    })
+}
+
+pub(crate) fn write_crypto(ctx: &DeviceContext, res: &mut String) {
+    if vpns(ctx).next().is_none() {
+        return;
+    }
+
+    res.push_str("crypto isakmp policy 10\n");
+    res.push_str("   encryption aes 256\n   hash sha\n   authentication pre-share\n   group 5\n");
+    res.push_str("exit\n");
+    writeln!(
+        res,
+        "crypto ipsec transform-set {TRANSFORM_SET} esp-aes esp-sha-hmac"
+    )
+    .unwrap();
+
+    for (i, (vpn, own, other)) in vpns(ctx).enumerate() {
+        let peer_ip = outside_link(ctx, other).close_ip.addr();
+        let peer_name = &ctx.app.devices[other.router].name;
+        let acl = format!("VPN-{peer_name}");
+
+        writeln!(
+            res,
+            "crypto isakmp key {} address {peer_ip}",
+            vpn.pre_shared_key
+        )
+        .unwrap();
+
+        // The traffic between the networks of the two sides is encrypted
+        writeln!(res, "ip access-list extended {acl}").unwrap();
+        for local in &own.networks {
+            for remote in &other.networks {
+                writeln!(
+                    res,
+                    "   permit ip {} {} {} {}",
+                    local.network(),
+                    local.hostmask(),
+                    remote.network(),
+                    remote.hostmask(),
+                )
+                .unwrap();
+            }
+        }
+        res.push_str("exit\n");
+
+        writeln!(res, "crypto map {CRYPTO_MAP} {} ipsec-isakmp", (i + 1) * 10).unwrap();
+        writeln!(res, "   set peer {peer_ip}").unwrap();
+        writeln!(res, "   set transform-set {TRANSFORM_SET}").unwrap();
+        writeln!(res, "   match address {acl}").unwrap();
+        res.push_str("exit\n");
+    }
+    res.push('\n');
+}
+
+pub(crate) fn write_interface(ctx: &DeviceContext, res: &mut String, link: &DirectedLink) {
+    if vpns(ctx).any(|(_, own, _)| own.outside == link.far_key) {
+        writeln!(res, "   crypto map {CRYPTO_MAP}").unwrap();
+    }
+}
+
+/// The link the tunnel leaves a peer through, seen from the peer
+fn outside_link(ctx: &DeviceContext, peer: &VpnPeer) -> DirectedLink {
+    ctx.app
+        .get_directed_link(peer.router, peer.outside)
+        .expect("A VPN peer must be connected to its outside neighbour")
+}